@@ -0,0 +1,153 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::spell_check::Misspelling;
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// Findings that are accepted for now, counted per file and word. Counting occurrences instead
+/// of recording lines keeps the baseline valid while the files around the findings are edited,
+/// and still catches a word that is misspelled once more than before.
+#[derive(Debug, Default, PartialEq)]
+pub struct Baseline {
+    /// Accepted occurrences of each word, keyed by file name and then by the word as found.
+    counts: BTreeMap<String, BTreeMap<String, usize>>,
+    /// File names are stored relative to this directory, so the baseline works from any
+    /// directory of the project.
+    root: PathBuf,
+}
+
+impl Baseline {
+    /// Creates an empty baseline to be stored at `path`.
+    pub fn new(path: &Path) -> Self {
+        Self {
+            counts: BTreeMap::new(),
+            root: root_of(path),
+        }
+    }
+
+    /// Reads the baseline stored at `path`.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents: String = fs::read_to_string(path)
+            .map_err(|err| format!("{}: {}; create it with --update-baseline", path.display(), err))?;
+
+        Ok(Self {
+            counts: serde_json::from_str(&contents).map_err(|err| format!("{}: {}", path.display(), err))?,
+            root: root_of(path),
+        })
+    }
+
+    /// Writes the baseline to `path` as JSON, with files and words sorted so that it diffs well.
+    pub fn to_file(&self, path: &Path) -> Result<()> {
+        let json: String = serde_json::to_string_pretty(&self.counts)?;
+        fs::write(path, json + "\n").map_err(|err| format!("{}: {}", path.display(), err))?;
+
+        Ok(())
+    }
+
+    /// The number of accepted findings.
+    pub fn len(&self) -> usize {
+        self.counts.values().flat_map(|words| words.values()).sum()
+    }
+
+    /// Accepts every finding of `misspellings` in `file_name`.
+    pub fn record(&mut self, file_name: &str, misspellings: &[Misspelling]) {
+        let key: String = self.key(file_name);
+        for misspelling in misspellings {
+            *self.counts.entry(key.clone()).or_default().entry(misspelling.word.clone()).or_default() += 1;
+        }
+    }
+
+    /// Removes the findings of `misspellings` in `file_name` that the baseline accepts and
+    /// returns the rest. Each accepted occurrence is used up, so the findings of a file must
+    /// only be passed once.
+    pub fn filter(&mut self, file_name: &str, misspellings: Vec<Misspelling>) -> Vec<Misspelling> {
+        let key: String = self.key(file_name);
+        let Some(words) = self.counts.get_mut(&key) else {
+            return misspellings;
+        };
+
+        misspellings
+            .into_iter()
+            .filter(|misspelling| match words.get_mut(&misspelling.word) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    false
+                }
+                _ => true,
+            })
+            .collect()
+    }
+
+    fn key(&self, file_name: &str) -> String {
+        match absolute(Path::new(file_name)).strip_prefix(&self.root) {
+            Ok(relative) => relative.to_string_lossy().replace('\\', "/"),
+            Err(_) => file_name.to_string(),
+        }
+    }
+}
+
+fn root_of(path: &Path) -> PathBuf {
+    match path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        Some(parent) => absolute(parent),
+        None => absolute(Path::new(".")),
+    }
+}
+
+/// Resolves `path` the same way wherever it is given from, falling back to joining it to the
+/// working directory when it does not exist.
+fn absolute(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| env::current_dir().map_or(path.to_path_buf(), |cwd| cwd.join(path)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf};
+
+    use super::Baseline;
+    use crate::{
+        dictionary::Dictionary,
+        processor,
+        spell_check::{Misspelling, SpellCheck},
+    };
+
+    fn misspellings(text: &str) -> Vec<Misspelling> {
+        let words: Vec<String> = vec!["hello".to_string(), "world".to_string()];
+        let spell_check: SpellCheck = SpellCheck::from(&Dictionary::from((words, 255)));
+
+        spell_check.misspelled_tokens(text, processor::tokenize(text, processor::NON_WORDS), 0, 0)
+    }
+
+    #[test]
+    fn test_baseline() {
+        let root: PathBuf = env::temp_dir().join("spell_sweep_baseline_test");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("docs")).expect("Failed to create directory");
+        let path: PathBuf = root.join("baseline.json");
+        let file_name: String = root.join("docs/notes.txt").display().to_string();
+        fs::write(&file_name, "").expect("Failed to write file");
+
+        let mut baseline: Baseline = Baseline::new(&path);
+        baseline.record(&file_name, &misspellings("helo wrold helo"));
+        assert_eq!(baseline.len(), 3);
+        baseline.to_file(&path).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "{\n  \"docs/notes.txt\": {\n    \"helo\": 2,\n    \"wrold\": 1\n  }\n}\n"
+        );
+
+        let mut baseline: Baseline = Baseline::from_file(&path).unwrap();
+        let remaining: Vec<Misspelling> = baseline.filter(&file_name, misspellings("wrold helo helo helo wordl"));
+        let words: Vec<&str> = remaining.iter().map(|misspelling| misspelling.word.as_str()).collect();
+        assert_eq!(words, vec!["helo", "wordl"]);
+        assert_eq!(baseline.filter("other.txt", misspellings("helo")).len(), 1);
+        assert!(Baseline::from_file(&root.join("missing.json")).is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
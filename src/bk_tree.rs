@@ -1,7 +1,7 @@
-use std::{error::Error, fs::File, io::{BufReader, BufWriter, Read, Write}, rc::Rc};
+use std::{collections::HashMap, error::Error, fs::File, io::{BufReader, Read}, path::Path, sync::Arc};
 use rkyv::{AlignedVec, Archive, Deserialize, Serialize};
 
-use crate::dictionary::Dictionary;
+use crate::{dictionary::Dictionary, rewrite};
 
 #[derive(Clone, Debug, Archive, Serialize, Deserialize, PartialEq)]
 #[archive(compare(PartialEq), check_bytes)]
@@ -9,16 +9,29 @@ use crate::dictionary::Dictionary;
 #[readonly::make]
 pub struct Node {
     pub word: NodeString,
-    pub next: Vec<Option<u32>>
+    pub next: Vec<Option<u32>>,
+    /// Whether the word may be offered as a suggestion, or is only accepted.
+    pub suggest: bool
 }
 
-type NodeString = Option<Rc<String>>;
+type NodeString = Option<Arc<String>>;
+
+/// Replaces `from` with `to` to turn a common misspelling into its correction. `^` and `$` anchor
+/// `from` to the start and end of the word, and `_` in `to` stands for a space.
+#[derive(Clone, Debug, Archive, Serialize, Deserialize, PartialEq)]
+#[archive(compare(PartialEq), check_bytes)]
+#[archive_attr(derive(Debug))]
+pub struct Replacement {
+    pub from: String,
+    pub to: String
+}
 
 impl Node {
     pub fn new(word: NodeString, max_word_length: usize) -> Self {
         Self {
             word,
-            next: vec![None; max_word_length + 1]
+            next: vec![None; max_word_length + 1],
+            suggest: true
         }
     }
 }
@@ -31,7 +44,9 @@ pub struct BKTree {
     pub max_word_length: u16,
     pub alphabet_length: u16,
     pub tree: Vec<Node>,
-    pub size: u32
+    pub size: u32,
+    /// Replacements that turn a common misspelling into its correction, lowercased.
+    pub replacements: Vec<Replacement>
 }
 
 impl BKTree {
@@ -40,11 +55,14 @@ impl BKTree {
             max_word_length,
             alphabet_length,
             tree: vec![Node::new(None, max_word_length as usize); max_words],
-            size: 0
+            size: 0,
+            replacements: Vec::new()
         }
     }
 
-    fn get_damerau_levenshtein_distance(&self, a: &str, b: &str) -> Result<u8, Box<dyn Error>> {
+    pub fn get_damerau_levenshtein_distance(&self, a: &str, b: &str) -> Result<u8, Box<dyn Error>> {
+        let a: Vec<char> = a.chars().collect();
+        let b: Vec<char> = b.chars().collect();
         let m: usize = a.len();
         let n: usize = b.len();
 
@@ -62,16 +80,21 @@ impl BKTree {
             dp[0][j + 1] = infinity;
         }
 
+        // Characters outside the alphabet are rare enough that a map is cheaper than growing `da`.
         let mut da: Vec<usize> = vec![0; self.alphabet_length as usize];
+        let mut da_overflow: HashMap<char, usize> = HashMap::new();
 
         for i in 1..=m {
             let mut db: usize = 0;
             for j in 1..=n {
-                let k: usize = da[b.chars().nth(j - 1).ok_or("Couldn't get the (j - 1)th character of b")? as usize];
+                let b_char: char = b[j - 1];
+                let k: usize = match da.get(b_char as usize) {
+                    Some(&k) => k,
+                    None => da_overflow.get(&b_char).copied().unwrap_or(0),
+                };
                 let l: usize = db;
 
-                let a_char: char = a.chars().nth(i - 1).ok_or("Couldn't get the (i - 1)th character of a")?;
-                let b_char: char = b.chars().nth(j - 1).ok_or("Couldn't get the (j - 1)th character of b")?;
+                let a_char: char = a[i - 1];
                 let cost: usize = if a_char == b_char { 0 } else { 1 };
                 db = if cost == 0 { j } else { db };
 
@@ -86,13 +109,24 @@ impl BKTree {
                     )
                 );
             }
-            da[a.chars().nth(i - 1).ok_or("Couldn't get the (i - 1)th character of a")? as usize] = i;
+            match da.get_mut(a[i - 1] as usize) {
+                Some(entry) => *entry = i,
+                None => {
+                    da_overflow.insert(a[i - 1], i);
+                }
+            }
         }
 
-        Ok(dp[m + 1][n + 1] as u8)
+        Ok(dp[m + 1][n + 1].min(u8::MAX as usize) as u8)
     }
 
-    pub fn add(&mut self, word: Rc<String>) -> Result<(), Box<dyn Error>> {
+    pub fn add(&mut self, word: Arc<String>) -> Result<(), Box<dyn Error>> {
+        self.add_word(word, true)
+    }
+
+    /// Adds `word`, which is only offered as a suggestion if `suggest` is set. A word added both
+    /// ways is not suggested.
+    pub fn add_word(&mut self, word: Arc<String>, suggest: bool) -> Result<(), Box<dyn Error>> {
         let mut current: usize = 0;
         let mut distance: u8;
 
@@ -101,16 +135,20 @@ impl BKTree {
                 Some(w) => w,
                 None => ""
             };
-            distance = self.get_damerau_levenshtein_distance(&current_word, &word)?;
+            distance = self.get_damerau_levenshtein_distance(current_word, &word)?;
 
             match distance {
-                0 => break,
+                0 => {
+                    self.tree[current].suggest &= suggest;
+                    break;
+                },
                 d => {
                     match self.tree[current].next[d as usize] {
                         Some(n) => current = n as usize,
                         None => {
-                            if !self.tree[current].word.is_none() { self.tree[current].next[d as usize] = Some(self.size); }
+                            if self.tree[current].word.is_some() { self.tree[current].next[d as usize] = Some(self.size); }
                             self.tree[self.size as usize].word = Some(word);
+                            self.tree[self.size as usize].suggest = suggest;
                             self.size += 1;
                             break;
                         },
@@ -131,15 +169,14 @@ impl BKTree {
                 Some(w) => w,
                 None => ""
             };
-            distance = self.get_damerau_levenshtein_distance(&current_word, &word)?;
+            distance = self.get_damerau_levenshtein_distance(current_word, word)?;
 
             match distance {
                 0 => return Ok(true),
-                d if self.tree[current].next[d as usize].is_none() => return Ok(false),
                 d => {
-                    match self.tree[current].next[d as usize] {
-                        Some(n) => current = n as usize,
-                        None => return Ok(false)
+                    match self.tree[current].next.get(d as usize) {
+                        Some(Some(n)) => current = *n as usize,
+                        _ => return Ok(false)
                     }
                 },
             }
@@ -157,19 +194,18 @@ impl BKTree {
                 Some(w) => w,
                 None => ""
             };
-            let distance: u8 = self.get_damerau_levenshtein_distance(&word, &current_word)?;
+            let distance: u8 = self.get_damerau_levenshtein_distance(word, current_word)?;
 
-            if distance <= tolerance {
+            if distance <= tolerance && self.tree[current].suggest {
                 result.push(current_word);
             }
 
             let tolerance_start: u8 = if distance > tolerance { distance - tolerance } else { 1 };
-            let tolerance_end: u8 = distance + tolerance;
+            let tolerance_end: u8 = distance.saturating_add(tolerance);
 
             for i in tolerance_start..=tolerance_end {
-                if self.tree[current].next[i as usize].is_some() {
-                    stack.push(self.tree[current].next[i as usize]
-                            .ok_or("Couldn't push element to stack")? as usize);
+                if let Some(Some(next)) = self.tree[current].next.get(i as usize) {
+                    stack.push(*next as usize);
                 }
             }
         }
@@ -177,13 +213,47 @@ impl BKTree {
         Ok(result)
     }
 
+    pub fn get_ranked_similar_words(&self, word: &str, tolerance: u8) -> Result<Vec<(&str, u8)>, Box<dyn Error>> {
+        let mut ranked: Vec<(&str, u8)> = Vec::new();
+
+        for similar_word in self.get_similar_words(word, tolerance)? {
+            let distance: u8 = self.get_damerau_levenshtein_distance(word, similar_word)?;
+            ranked.push((similar_word, distance));
+        }
+
+        let word_length: usize = word.chars().count();
+        let length_difference = |similar_word: &str| similar_word.chars().count().abs_diff(word_length);
+        ranked.sort_by(|a, b| {
+            a.1.cmp(&b.1)
+                .then(length_difference(a.0).cmp(&length_difference(b.0)))
+                .then(a.0.cmp(b.0))
+        });
+
+        Ok(ranked)
+    }
+
+    pub fn from_file(file_path: &str) -> Result<Self, Box<dyn Error>> {
+        let bytes: Vec<u8> = std::fs::read(file_path)?;
+        let tree: BKTree = rkyv::from_bytes::<BKTree>(&bytes).map_err(|err| err.to_string())?;
+
+        Ok(tree)
+    }
+
+    /// Reads how many words the tree at `file_path` holds and how long the longest one is, without
+    /// deserializing the tree itself.
+    pub fn counts_from_file(file_path: &str) -> Result<(u32, u16), Box<dyn Error>> {
+        let mut bytes: AlignedVec = AlignedVec::new();
+        bytes.extend_from_slice(&std::fs::read(file_path)?);
+        let tree: &ArchivedBKTree = rkyv::check_archived_root::<BKTree>(&bytes).map_err(|err| err.to_string())?;
+
+        Ok((tree.size, tree.max_word_length))
+    }
+
+    /// Writes the tree to `file_path` atomically, so a concurrent or interrupted run never
+    /// leaves a truncated file behind for the next one to load.
     pub fn to_file(&self, file_path: &str) -> Result<(), Box<dyn Error>> {
         let bytes: AlignedVec = rkyv::to_bytes::<_, 256>(self)?;
-        let file: File = std::fs::File::create(file_path)?; 
-        let mut writer: BufWriter<File> = BufWriter::new(file);
-        writer.write_all(&bytes)?;
-
-        Ok(())
+        rewrite::write_atomic(Path::new(file_path), &bytes)
     }
 }
 
@@ -192,8 +262,13 @@ impl From<&Dictionary> for BKTree {
         let mut tree: BKTree = BKTree::new(value.max_word_length, value.alphabet_length, value.words.len());
     
         for word in value.words.iter() {
-            tree.add(Rc::clone(&word)).expect("Failed to add word to tree");
+            tree.add_word(Arc::clone(word), !value.no_suggest.contains(word.as_str())).expect("Failed to add word to tree");
         }
+        tree.replacements = value
+            .replacements
+            .iter()
+            .map(|(from, to)| Replacement { from: from.clone(), to: to.clone() })
+            .collect();
         
         tree
     }
@@ -215,7 +290,7 @@ impl From<File> for BKTree {
 mod tests {
     use std::error::Error;
     use std::fs::File;
-    use std::rc::Rc;
+    use std::sync::Arc;
     use super::BKTree;
     use super::super::dictionary::Dictionary;
 
@@ -232,8 +307,8 @@ mod tests {
         assert_eq!(tree.size, dictionary.words.len() as u32);
 
         for word in dictionary.words.iter() {
-            assert!(tree.does_contain(&word).unwrap());
-            assert_eq!(Rc::strong_count(&word), 2);
+            assert!(tree.does_contain(word).unwrap());
+            assert_eq!(Arc::strong_count(word), 2);
         }
     }
 
@@ -241,11 +316,11 @@ mod tests {
     fn test_similar_words() -> Result<(), Box<dyn Error>> {
         let mut tree: BKTree = BKTree::new(5, 255, 5);
 
-        tree.add(Rc::new("hello".to_string()))?;
-        tree.add(Rc::new("world".to_string()))?;
-        tree.add(Rc::new("hella".to_string()))?;
-        tree.add(Rc::new("hell".to_string()))?;
-        tree.add(Rc::new("help".to_string()))?;
+        tree.add(Arc::new("hello".to_string()))?;
+        tree.add(Arc::new("world".to_string()))?;
+        tree.add(Arc::new("hella".to_string()))?;
+        tree.add(Arc::new("hell".to_string()))?;
+        tree.add(Arc::new("help".to_string()))?;
 
         let similar_words: Vec<&str> = tree.get_similar_words("hell", 1).expect("Failed to get similar words");
         assert_eq!(similar_words.len(), 4);
@@ -257,15 +332,46 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_ranked_similar_words() -> Result<(), Box<dyn Error>> {
+        let mut tree: BKTree = BKTree::new(5, 255, 4);
+
+        tree.add(Arc::new("hello".to_string()))?;
+        tree.add(Arc::new("help".to_string()))?;
+        tree.add(Arc::new("hell".to_string()))?;
+        tree.add(Arc::new("world".to_string()))?;
+
+        let ranked: Vec<(&str, u8)> = tree.get_ranked_similar_words("helo", 2)?;
+        assert_eq!(ranked, vec![("hell", 1), ("help", 1), ("hello", 1)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_non_ascii_and_long_words() -> Result<(), Box<dyn Error>> {
+        let mut tree: BKTree = BKTree::new(6, 255, 3);
+
+        tree.add(Arc::new("naïve".to_string()))?;
+        tree.add(Arc::new("café".to_string()))?;
+        tree.add(Arc::new("cafe".to_string()))?;
+
+        assert!(tree.does_contain("naïve")?);
+        assert!(!tree.does_contain("don\u{2019}t")?);
+        assert!(!tree.does_contain("supercalifragilisticexpialidocious")?);
+        assert_eq!(tree.get_ranked_similar_words("cafè", 1)?, vec![("cafe", 1), ("café", 1)]);
+
+        Ok(())
+    }
+
     #[test]
     fn test_file_serialization() -> Result<(), Box<dyn Error>> {
         let mut tree: BKTree = BKTree::new(5, 255, 5);
 
-        tree.add(Rc::new("hello".to_string()))?;
-        tree.add(Rc::new("world".to_string()))?;
-        tree.add(Rc::new("hella".to_string()))?;
-        tree.add(Rc::new("hell".to_string()))?;
-        tree.add(Rc::new("help".to_string()))?;
+        tree.add(Arc::new("hello".to_string()))?;
+        tree.add(Arc::new("world".to_string()))?;
+        tree.add(Arc::new("hella".to_string()))?;
+        tree.add(Arc::new("hell".to_string()))?;
+        tree.add(Arc::new("help".to_string()))?;
 
         tree.to_file("bk_tree_test.bin")?;
 
@@ -273,6 +379,7 @@ mod tests {
         let new_tree: BKTree = BKTree::from(file);
         
         assert_eq!(tree, new_tree);
+        assert_eq!(BKTree::counts_from_file("bk_tree_test.bin")?, (5, 5));
         std::fs::remove_file("bk_tree_test.bin").expect("Failed to remove BKTree file");
         Ok(())
     }
@@ -3,12 +3,15 @@ use std::{
     error::Error,
     f32::consts::{E, LN_2},
     fs::{self, File},
-    io::{Read, Write},
+    io::Read,
     path::Path,
 };
 use rkyv::{AlignedVec, Archive, Deserialize, Serialize};
 
-use crate::{dictionary::Dictionary, utils};
+use crate::{dictionary::Dictionary, rewrite, utils};
+
+/// False positive rate a bloom filter is sized for unless configured otherwise.
+pub const DEFAULT_FP_PROB: f32 = 0.01;
 
 #[derive(Debug, Serialize, Deserialize, Archive, PartialEq)]
 #[archive(compare(PartialEq), check_bytes)]
@@ -26,14 +29,14 @@ impl BloomFilter {
         let a = (items_count as f32) * fp_prob.log(E);
         let b = LN_2.powi(2);
 
-        return (-a / b).ceil() as u64;
+        (-a / b).ceil() as u64
     }
 
     fn get_hash_count(items_count: u32, fp_prob: f32) -> u32 {
         let a = Self::get_size(items_count, fp_prob) as f32 * LN_2;
         let b = items_count as f32;
 
-        return (a / b).ceil() as u32;
+        (a / b).ceil() as u32
     }
 
     pub fn new(items_count: u32, fp_prob: f32) -> BloomFilter {
@@ -67,7 +70,7 @@ impl BloomFilter {
                 return false;
             }
         }
-        return true;
+        true
     }
 
     fn serialize(&self) -> Result<AlignedVec, Box<dyn Error>> {
@@ -78,14 +81,10 @@ impl BloomFilter {
         Ok(utils::deserialize::<Self>(bytes)?)
     }
 
+    /// Writes the filter to `path` atomically, like [`crate::bk_tree::BKTree::to_file`].
     pub fn to_file(&self, path: &str) -> Result<(), Box<dyn Error>> {
         let bytes = self.serialize()?;
-        let bytes = bytes.as_slice();
-
-        let mut file = fs::File::create(Path::new(path))?;
-        file.write_all(bytes)?;
-
-        Ok(())
+        rewrite::write_atomic(Path::new(path), bytes.as_slice())
     }
 
     pub fn from_file(path: &str) -> Result<Self, Box<dyn Error>> {
@@ -118,8 +117,14 @@ impl From<File> for BloomFilter {
 
 impl From<&Dictionary> for BloomFilter {
     fn from(value: &Dictionary) -> Self {
-        let mut bf = Self::new(value.words.len() as u32, 0.01);
-        for word in value.words.iter() {
+        Self::from((value, DEFAULT_FP_PROB))
+    }
+}
+
+impl From<(&Dictionary, f32)> for BloomFilter {
+    fn from(value: (&Dictionary, f32)) -> Self {
+        let mut bf = Self::new(value.0.words.len() as u32, value.1);
+        for word in value.0.words.iter() {
             bf.insert(word.as_str())
         }
         bf
@@ -151,7 +156,7 @@ mod tests {
         ];
 
         for w in word_present.iter() {
-            assert_eq!(bf.lookup(w), true);
+            assert!(bf.lookup(w));
         }
 
         let (mut true_pos, mut true_neg, mut false_pos) = (0, 0, 0);
@@ -200,7 +205,7 @@ mod tests {
         let bf = BloomFilter::from(&dictionary);
 
         for word in words_absent {
-            assert_eq!(bf.lookup(word), false);
+            assert!(!bf.lookup(word));
         }
     }
 }
@@ -0,0 +1,328 @@
+use std::{
+    env,
+    error::Error,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+};
+
+use crate::{bk_tree::BKTree, bloom_filter::BloomFilter, config::Config, dictionary::Dictionary, hunspell};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// The word list used when no dictionary is configured.
+pub const DEFAULT_DICTIONARY: &str = "dictionary.txt";
+
+/// The directory name used below the data and cache directories.
+const APP_NAME: &str = "spell_sweep";
+
+/// Bumped whenever the layout of the artifacts changes, so that older ones are not loaded.
+const ARTIFACT_VERSION: u32 = 2;
+
+/// Where the built BK-tree and bloom filter are read from, or written to if they do not exist.
+#[derive(Debug, PartialEq)]
+#[readonly::make]
+pub struct Artifacts {
+    pub bk_tree: PathBuf,
+    pub bloom_filter: PathBuf,
+}
+
+impl Artifacts {
+    /// Picks the artifact paths for `dictionaries`. Paths set in the configuration are used as
+    /// they are; otherwise the artifacts live in a directory of the cache directory named after a
+    /// hash of the dictionaries and the settings they are built with. Nothing is created, so
+    /// locating the artifacts to report on them leaves the cache directory as it is.
+    pub fn locate(config: &Config, dictionaries: &[PathBuf]) -> Result<Self> {
+        if let (Some(bk_tree), Some(bloom_filter)) = (&config.bk_tree, &config.bloom_filter) {
+            return Ok(Self {
+                bk_tree: bk_tree.clone(),
+                bloom_filter: bloom_filter.clone(),
+            });
+        }
+
+        let cache_dir: PathBuf = match &config.cache_dir {
+            Some(cache_dir) => cache_dir.clone(),
+            None => cache_dir(env_var).ok_or("Could not find a cache directory; set XDG_CACHE_HOME or use --cache-dir")?,
+        };
+        let key: u64 = dictionary_hash(dictionaries, config.alphabet_length, config.false_positive_rate)?;
+        let directory: PathBuf = cache_dir.join(format!("{:016x}", key));
+
+        Ok(Self {
+            bk_tree: config.bk_tree.clone().unwrap_or_else(|| directory.join("bk_tree.bin")),
+            bloom_filter: config.bloom_filter.clone().unwrap_or_else(|| directory.join("bloom_filter.bin")),
+        })
+    }
+
+    pub fn exist(&self) -> bool {
+        self.bk_tree.exists() && self.bloom_filter.exists()
+    }
+
+    /// Creates the directories the artifacts are written to.
+    pub fn create_dirs(&self) -> Result<()> {
+        for path in [&self.bk_tree, &self.bloom_filter] {
+            if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+                fs::create_dir_all(parent).map_err(|err| format!("{}: {}", parent.display(), err))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Builds the BK-tree and bloom filter from `dictionary` and writes them.
+    pub fn build(&self, dictionary: &Dictionary, fp_prob: f32) -> Result<()> {
+        self.create_dirs()?;
+        BKTree::from(dictionary)
+            .to_file(&self.bk_tree.to_string_lossy())
+            .map_err(|err| format!("{}: {}", self.bk_tree.display(), err))?;
+        BloomFilter::from((dictionary, fp_prob))
+            .to_file(&self.bloom_filter.to_string_lossy())
+            .map_err(|err| format!("{}: {}", self.bloom_filter.display(), err))?;
+
+        Ok(())
+    }
+}
+
+/// Reads the words of every dictionary into one. A `.dic` file with an `.aff` file next to it
+/// is read as a hunspell dictionary, and every other file as a word list.
+pub fn load_dictionary(dictionaries: &[PathBuf], alphabet_length: u16) -> Result<Dictionary> {
+    let mut words: Vec<String> = Vec::new();
+    let mut no_suggest: Vec<String> = Vec::new();
+    let mut replacements: Vec<(String, String)> = Vec::new();
+    for dictionary in dictionaries {
+        if let Some(affix_file) = hunspell::affix_file(dictionary) {
+            let expansion: hunspell::Expansion = hunspell::load(dictionary, &affix_file)?;
+            words.extend(expansion.words);
+            no_suggest.extend(expansion.no_suggest);
+            replacements.extend(expansion.replacements);
+            continue;
+        }
+        let file: File = File::open(dictionary).map_err(|err| format!("{}: {}", dictionary.display(), err))?;
+        words.extend(BufReader::new(file).lines().map_while(io::Result::ok));
+    }
+
+    Ok(Dictionary::from((words, alphabet_length)).with_rules(no_suggest, replacements))
+}
+
+/// The files a dictionary is read from: the dictionary itself, and the affix file of a hunspell
+/// dictionary.
+pub fn dictionary_files(dictionaries: &[PathBuf]) -> Vec<PathBuf> {
+    dictionaries
+        .iter()
+        .flat_map(|dictionary| [Some(dictionary.clone()), hunspell::affix_file(dictionary)])
+        .flatten()
+        .collect()
+}
+
+/// The personal word list that `add` writes to, which is accepted on top of the dictionary.
+pub fn personal_words() -> Option<PathBuf> {
+    data_dirs(env_var).into_iter().next().map(|data_dir| data_dir.join("words.txt"))
+}
+
+/// Resolves the configured dictionaries, or `dictionary.txt` if there are none. A dictionary that
+/// does not exist as given is looked up in the data directories.
+pub fn find_dictionaries(config: &Config) -> Result<Vec<PathBuf>> {
+    let default: [PathBuf; 1] = [PathBuf::from(DEFAULT_DICTIONARY)];
+    let dictionaries: &[PathBuf] = match config.dictionaries.is_empty() {
+        true => &default,
+        false => &config.dictionaries,
+    };
+
+    dictionaries
+        .iter()
+        .map(|dictionary| find_dictionary(dictionary, &data_dirs(env_var)))
+        .collect()
+}
+
+/// Looks up a dictionary named after the language `name` in the data directories, either a
+/// hunspell dictionary like `de_DE.dic` or a word list like `de_DE.txt`.
+pub fn find_language_dictionary<F: Fn(&str) -> Option<String>>(name: &str, var: F) -> Option<PathBuf> {
+    let names: [String; 2] = [name.to_string(), name.replace('-', "_")];
+    let data_dirs: Vec<PathBuf> = data_dirs(var);
+
+    names
+        .iter()
+        .flat_map(|name| [format!("{}.dic", name), format!("{}.txt", name)])
+        .find_map(|file_name| data_dirs.iter().map(|data_dir| data_dir.join(&file_name)).find(|path| path.is_file()))
+}
+
+fn find_dictionary(dictionary: &Path, data_dirs: &[PathBuf]) -> Result<PathBuf> {
+    if dictionary.is_file() || dictionary.is_absolute() {
+        return Ok(dictionary.to_path_buf());
+    }
+
+    data_dirs
+        .iter()
+        .map(|data_dir| data_dir.join(dictionary))
+        .find(|path| path.is_file())
+        .ok_or_else(|| {
+            let searched: Vec<String> = data_dirs.iter().map(|data_dir| data_dir.display().to_string()).collect();
+            format!(
+                "Dictionary {} not found in the working directory or in {}",
+                dictionary.display(),
+                searched.join(", ")
+            )
+            .into()
+        })
+}
+
+pub fn env_var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+/// The directories dictionaries are looked up in, most specific first, following the XDG base
+/// directory specification.
+fn data_dirs<F: Fn(&str) -> Option<String>>(var: F) -> Vec<PathBuf> {
+    let data_home: Option<PathBuf> = var("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| var("HOME").map(|home| Path::new(&home).join(".local/share")));
+    let data_dirs: String = var("XDG_DATA_DIRS").unwrap_or("/usr/local/share:/usr/share".to_string());
+
+    data_home
+        .into_iter()
+        .chain(data_dirs.split(':').filter(|dir| !dir.is_empty()).map(PathBuf::from))
+        .map(|dir| dir.join(APP_NAME))
+        .collect()
+}
+
+fn cache_dir<F: Fn(&str) -> Option<String>>(var: F) -> Option<PathBuf> {
+    var("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| var("HOME").map(|home| Path::new(&home).join(".cache")))
+        .map(|dir| dir.join(APP_NAME))
+}
+
+/// The 64-bit FNV-1a hash, which unlike `DefaultHasher` is the same across Rust releases, so
+/// cache keys stay valid.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    fn new() -> Self {
+        Self(Self::OFFSET_BASIS)
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(Self::PRIME);
+        }
+    }
+}
+
+/// Hashes the contents of `dictionaries`, along with everything else the artifacts depend on, so
+/// that changing any of it builds them again.
+fn dictionary_hash(dictionaries: &[PathBuf], alphabet_length: u16, fp_prob: f32) -> Result<u64> {
+    let mut hash: Fnv1a = Fnv1a::new();
+    hash.update(env!("CARGO_PKG_VERSION").as_bytes());
+    hash.update(&ARTIFACT_VERSION.to_le_bytes());
+    hash.update(&alphabet_length.to_le_bytes());
+    hash.update(&fp_prob.to_le_bytes());
+
+    let mut buffer: Vec<u8> = vec![0; 1 << 16];
+    for dictionary in dictionary_files(dictionaries).iter() {
+        let mut file: File = File::open(dictionary).map_err(|err| format!("{}: {}", dictionary.display(), err))?;
+        loop {
+            let read: usize = file.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            hash.update(&buffer[..read]);
+        }
+        // Keeps ["ab", "c"] and ["a", "bc"] apart.
+        hash.update(&[0]);
+    }
+
+    Ok(hash.0)
+}
+
+/// The socket a daemon serving `dictionaries` with the settings of `config` listens on, unless
+/// the configuration names one. Daemons for other dictionaries get other sockets, so a check
+/// only ever talks to a daemon that gives the same answers. The key covers the paths rather than
+/// the contents, so a daemon keeps its socket when it reloads a changed dictionary.
+pub fn socket_path(config: &Config, dictionaries: &[PathBuf]) -> Option<PathBuf> {
+    if let Some(socket) = &config.socket {
+        return Some(socket.clone());
+    }
+
+    let mut hash: Fnv1a = Fnv1a::new();
+    hash.update(env!("CARGO_PKG_VERSION").as_bytes());
+    hash.update(&config.alphabet_length.to_le_bytes());
+    hash.update(&config.false_positive_rate.to_le_bytes());
+    // The personal word list is left out, since it is picked up once it exists.
+    let personal_words: Option<PathBuf> = personal_words();
+    let word_lists = config.words.iter().filter(|path| Some(*path) != personal_words.as_ref());
+    for path in dictionaries.iter().chain(word_lists) {
+        let path: PathBuf = path.canonicalize().unwrap_or(path.clone());
+        hash.update(path.to_string_lossy().as_bytes());
+        hash.update(&[0]);
+    }
+
+    env_var("XDG_RUNTIME_DIR")
+        .map(|dir| Path::new(&dir).join(APP_NAME))
+        .or_else(|| cache_dir(env_var))
+        .map(|dir| dir.join(format!("{:016x}.sock", hash.0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf};
+
+    use super::{cache_dir, data_dirs, dictionary_hash, find_dictionary, Artifacts};
+    use crate::{config::Config, dictionary::Dictionary};
+
+    #[test]
+    fn test_xdg_dirs() {
+        let var = |name: &str| match name {
+            "HOME" => Some("/home/ada".to_string()),
+            "XDG_DATA_DIRS" => Some("/opt/share::/usr/share".to_string()),
+            _ => None,
+        };
+
+        assert_eq!(
+            data_dirs(var),
+            vec![
+                PathBuf::from("/home/ada/.local/share/spell_sweep"),
+                PathBuf::from("/opt/share/spell_sweep"),
+                PathBuf::from("/usr/share/spell_sweep"),
+            ]
+        );
+        assert_eq!(cache_dir(var), Some(PathBuf::from("/home/ada/.cache/spell_sweep")));
+        assert_eq!(
+            cache_dir(|name: &str| (name == "XDG_CACHE_HOME").then(|| "/tmp/cache".to_string())),
+            Some(PathBuf::from("/tmp/cache/spell_sweep"))
+        );
+        assert_eq!(cache_dir(|_: &str| None), None);
+    }
+
+    #[test]
+    fn test_find_dictionary_and_hash() {
+        let root: PathBuf = env::temp_dir().join("spell_sweep_cache_test");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).expect("Failed to create directory");
+        let dictionary: PathBuf = root.join("en.txt");
+        fs::write(&dictionary, "hello\nworld\n").expect("Failed to write dictionary");
+
+        let data_dirs: Vec<PathBuf> = vec![root.join("missing"), root.clone()];
+        assert_eq!(find_dictionary(&PathBuf::from("en.txt"), &data_dirs).unwrap(), dictionary);
+        assert!(find_dictionary(&PathBuf::from("fr.txt"), &data_dirs).is_err());
+
+        let dictionaries: Vec<PathBuf> = vec![dictionary.clone()];
+        let hash: u64 = dictionary_hash(&dictionaries, 255, 0.01).unwrap();
+        assert_eq!(dictionary_hash(&dictionaries, 255, 0.01).unwrap(), hash);
+        assert_ne!(dictionary_hash(&dictionaries, 255, 0.02).unwrap(), hash);
+        fs::write(&dictionary, "hello\nworld\nhelp\n").expect("Failed to write dictionary");
+        assert_ne!(dictionary_hash(&dictionaries, 255, 0.01).unwrap(), hash);
+
+        let config: Config = Config {
+            cache_dir: Some(root.join("cache")),
+            ..Config::default()
+        };
+        let artifacts: Artifacts = Artifacts::locate(&config, &dictionaries).unwrap();
+        assert!(!root.join("cache").exists());
+        artifacts.build(&Dictionary::from((vec!["hello".to_string()], 255)), 0.01).unwrap();
+        assert!(artifacts.exist());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
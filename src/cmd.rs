@@ -1,89 +1,783 @@
 use std::{
+    collections::HashSet,
+    env,
     error::Error,
+    ffi::OsString,
     fs,
-    io::{self, BufRead, BufReader, Read},
-    path::PathBuf,
-    process,
+    io::{self, BufRead, BufReader},
+    path::{Path, PathBuf},
 };
 
-use clap::{command, value_parser, Arg};
+use clap::{command, parser::ValueSource, value_parser, Arg, ArgAction, ArgGroup, ArgMatches, Command};
+
+use crate::{
+    cache,
+    config::Config,
+    output::Format,
+    processor::{self, TokenClass},
+    prompt,
+    rewrite::Destination,
+    spell_check::Mode,
+    syntax::{self, source::Language, Syntax},
+    variants::{self, Locale},
+    walk::{self, Collected, WalkOptions},
+};
 
 type Result<T> = std::result::Result<T, Box<dyn Error>>;
 
-enum Data {
+/// Exit status when nothing was misspelled, or a command other than a check succeeded.
+pub const EXIT_CLEAN: i32 = 0;
+/// Exit status when misspelled words were found.
+pub const EXIT_MISSPELLED: i32 = 1;
+/// Exit status when the command could not be run, matching the status of usage errors.
+pub const EXIT_ERROR: i32 = 2;
+
+/// The parsed command line.
+#[readonly::make]
+pub struct Cli {
+    /// The project configuration, with the values given on the command line applied over it.
+    pub config: Config,
+    /// The language to check in, instead of the `lang` of the configuration.
+    pub lang: Option<String>,
+    pub subcommand: Subcommand,
+}
+
+/// What to do. Running without a subcommand is the same as `check`.
+pub enum Subcommand {
+    /// Checks files or piped input.
+    Check(Box<CmdData>),
+    /// Prints the ranked suggestions for each word.
+    Suggest { words: Vec<String>, format: Format },
+    /// Builds the BK-tree and bloom filter from the dictionary.
+    Build { force: bool },
+    /// Appends words to a word list.
+    Add { words: Vec<String>, list: PathBuf },
+    /// Keeps the dictionary loaded and answers requests on a Unix socket.
+    Serve,
+    /// Prints where the dictionary and its artifacts are and what they hold.
+    Stats { format: Format },
+    /// Serves diagnostics to an editor over the language server protocol. Only the settings that
+    /// pick which words are checked are used.
+    Lsp(Box<CmdData>),
+    /// Checks lines from stdin in the `ispell -a` pipe protocol, with the same settings as `Lsp`.
+    Pipe(Box<CmdData>),
+}
+
+#[readonly::make]
+pub struct CmdData {
+    pub inputs: Vec<Input>,
+    pub mode: Mode,
+    pub format: Format,
+    pub destination: Destination,
+    /// Forces every input to be read as this syntax instead of guessing it from the file name.
+    pub syntax: Option<Syntax>,
+    /// Which parts of each document are checked, and whether identifiers are split into subwords.
+    pub syntax_options: syntax::Options,
+    /// Subwords of identifiers that are accepted without being in the dictionary.
+    pub abbreviations: HashSet<String>,
+    /// The project configuration, with the values given on the command line applied over it.
+    pub config: Config,
+    /// Records the current findings as the baseline instead of reporting them.
+    pub update_baseline: bool,
+    /// Where interactive answers are read from instead of the terminal.
+    pub answers: Option<PathBuf>,
+    /// Some entries of the directories walked for inputs could not be read, so the run fails
+    /// once the inputs that were found are checked.
+    pub walk_failed: bool,
+}
+
+/// Settings for checking text handed over by an editor: plain reports, no inputs, and the
+/// default syntax options.
+impl Default for CmdData {
+    fn default() -> Self {
+        Self {
+            inputs: Vec::new(),
+            mode: Mode::Report,
+            format: Format::Text,
+            destination: Destination::Stdout,
+            syntax: None,
+            syntax_options: syntax::Options::default(),
+            abbreviations: HashSet::new(),
+            config: Config::default(),
+            update_baseline: false,
+            answers: None,
+            walk_failed: false,
+        }
+    }
+}
+
+impl CmdData {
+    pub fn syntax_of(&self, input: &Input) -> Syntax {
+        match (self.syntax, input) {
+            (Some(syntax), _) => syntax,
+            (None, Input::File(file_path)) => self
+                .config
+                .syntax_override(file_path)
+                .and_then(parse_syntax)
+                .unwrap_or_else(|| Syntax::from_path(file_path)),
+            (None, Input::Stdin) => Syntax::Text,
+        }
+    }
+
+    /// The language `input` is checked in where the document does not declare one, if an
+    /// override picks one for it.
+    pub fn language_of(&self, input: &Input) -> Option<&str> {
+        match input {
+            Input::File(file_path) => self.config.language_override(file_path),
+            Input::Stdin => None,
+        }
+    }
+}
+
+/// Where the text to check comes from. Nothing is read until the input is opened.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Input {
     File(PathBuf),
-    Pipe(Option<String>),
+    Stdin,
 }
 
-fn read_stdin() -> Result<Option<String>> {
-    if !atty::is(atty::Stream::Stdin) {
-        let stdin = io::stdin();
-        let mut reader = BufReader::new(stdin);
+impl Input {
+    /// The path of the file, which piped input does not have.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Input::File(file_path) => Some(file_path),
+            Input::Stdin => None,
+        }
+    }
+
+    pub fn file_name(&self) -> String {
+        match self {
+            Input::File(file_path) => file_path.display().to_string(),
+            Input::Stdin => "<stdin>".to_string(),
+        }
+    }
+
+    pub fn open(&self) -> Result<Box<dyn BufRead>> {
+        match self {
+            Input::File(file_path) => Ok(Box::new(BufReader::new(fs::File::open(file_path)?))),
+            Input::Stdin => Ok(Box::new(BufReader::new(io::stdin()))),
+        }
+    }
 
-        let mut buffer = String::new();
-        reader
-            .read_line(&mut buffer)
-            .expect("whatever I don't care");
+    pub fn read_to_end(&self) -> Result<Vec<u8>> {
+        let mut buffer = Vec::<u8>::new();
+        self.open()?.read_to_end(&mut buffer)?;
 
-        return Ok(Some(buffer.trim().to_string()));
+        Ok(buffer)
     }
 
-    Ok(None)
+    pub fn read_to_string(&self) -> Result<String> {
+        Ok(String::from_utf8_lossy(&self.read_to_end()?).to_string())
+    }
 }
 
-fn handle_input_data(input: Data) -> Result<String> {
-    match input {
-        Data::File(file_path) => handle_file(file_path),
-        Data::Pipe(piped_data) => Ok(handle_piped_data(piped_data)),
+fn stdin_input() -> Result<Input> {
+    if atty::is(atty::Stream::Stdin) {
+        return Err("Provide file path or pipe some data in.".into());
     }
+
+    Ok(Input::Stdin)
 }
 
-fn handle_file(file_path: PathBuf) -> Result<String> {
-    let mut file = fs::File::open(file_path)?;
+fn parse_mode(mode: &str, unambiguous: bool) -> Mode {
+    match mode {
+        "report" => Mode::Report,
+        "list" => Mode::List,
+        "fix" => Mode::Fix { unambiguous },
+        _ => Mode::Interactive,
+    }
+}
 
-    let mut buffer = Vec::<u8>::new();
+pub fn parse_syntax(syntax: &str) -> Option<Syntax> {
+    match syntax {
+        "text" => Some(Syntax::Text),
+        "markdown" => Some(Syntax::Markdown),
+        "html" | "xml" => Some(Syntax::Html),
+        "latex" => Some(Syntax::Latex),
+        "rust" => Some(Syntax::Source(Language::Rust)),
+        "python" => Some(Syntax::Source(Language::Python)),
+        "javascript" => Some(Syntax::Source(Language::JavaScript)),
+        "go" => Some(Syntax::Source(Language::Go)),
+        "c" => Some(Syntax::Source(Language::C)),
+        "shell" => Some(Syntax::Source(Language::Shell)),
+        _ => None,
+    }
+}
 
-    file.read_to_end(&mut buffer)?;
+const FORMATS: [&str; 5] = ["text", "json", "jsonl", "sarif", "checkstyle"];
 
-    Ok(String::from_utf8_lossy(buffer.as_slice())
-        .trim()
-        .to_string())
+fn parse_format(format: &str) -> Format {
+    match format {
+        "json" => Format::Json,
+        "jsonl" => Format::Jsonl,
+        "sarif" => Format::Sarif,
+        "checkstyle" => Format::Checkstyle,
+        _ => Format::Text,
+    }
 }
 
-fn handle_piped_data(piped_data: Option<String>) -> String {
-    match piped_data {
-        None => {
-            eprintln!("Error: Provide file path or pipe some data in.");
-            process::exit(1);
-        }
-        Some(data) => data,
+/// Flags shared by every subcommand, which set up the dictionary and the configuration.
+fn global_args() -> Vec<Arg> {
+    vec![
+        Arg::new("config")
+            .long("config")
+            .help("Read settings from this file instead of the closest .spellsweep.toml")
+            .value_parser(value_parser!(PathBuf))
+            .global(true),
+        Arg::new("no-config")
+            .long("no-config")
+            .help("Ignore .spellsweep.toml files")
+            .conflicts_with("config")
+            .action(ArgAction::SetTrue)
+            .global(true),
+        Arg::new("dictionary")
+            .long("dictionary")
+            .help("Word list to build the dictionary from, looked up in the data directories if not found; may be repeated")
+            .value_parser(value_parser!(PathBuf))
+            .action(ArgAction::Append)
+            .global(true),
+        Arg::new("lang")
+            .long("lang")
+            .help("Check in this language, using its profile from the configuration or a dictionary named after it")
+            .conflicts_with("dictionary")
+            .global(true),
+        Arg::new("cache-dir")
+            .long("cache-dir")
+            .help("Directory the built dictionary is cached in [default: $XDG_CACHE_HOME/spell_sweep]")
+            .value_parser(value_parser!(PathBuf))
+            .global(true),
+        Arg::new("no-daemon")
+            .long("no-daemon")
+            .help("Load the dictionary even if a spell_sweep serve daemon is running")
+            .action(ArgAction::SetTrue)
+            .global(true),
+        Arg::new("socket")
+            .long("socket")
+            .help("Socket of the daemon to serve on or connect to [default: one per dictionary in $XDG_RUNTIME_DIR/spell_sweep]")
+            .value_parser(value_parser!(PathBuf))
+            .global(true),
+        Arg::new("max-edit-distance")
+            .long("max-edit-distance")
+            .help("Only suggest words within this edit distance of the misspelled word")
+            .value_parser(value_parser!(u8).range(1..))
+            .global(true),
+        Arg::new("suggestions")
+            .long("suggestions")
+            .help("Offer at most this many suggestions per misspelled word")
+            .value_parser(value_parser!(usize))
+            .global(true),
+        Arg::new("locale")
+            .long("locale")
+            .help("Hold words to the spelling of this locale, so `colour` is misspelled under en-US")
+            .value_parser(variants::LOCALES)
+            .global(true),
+    ]
+}
+
+/// Flags of `check`, which are also accepted without a subcommand.
+fn check_args() -> Vec<Arg> {
+    vec![
+        Arg::new("pipe")
+            .short('a')
+            .long("pipe")
+            .help("Check lines from stdin in the ispell -a pipe protocol, for editors that drive ispell or aspell")
+            .conflicts_with_all(["filepath", "paths", "mode", "format", "write", "output", "diff", "update-baseline"])
+            .action(ArgAction::SetTrue),
+        Arg::new("filepath")
+            .short('f')
+            .long("file")
+            .help("Path to the source file")
+            .value_parser(value_parser!(PathBuf)),
+        Arg::new("paths")
+            .help("Files or directories to check; directories are walked recursively")
+            .num_args(0..)
+            .value_parser(value_parser!(PathBuf)),
+        Arg::new("include")
+            .long("include")
+            .help("Only check files that match this glob; may be repeated")
+            .action(ArgAction::Append),
+        Arg::new("exclude")
+            .long("exclude")
+            .help("Skip files in directories that match this glob; may be repeated")
+            .action(ArgAction::Append),
+        Arg::new("hidden")
+            .long("hidden")
+            .help("Check hidden files and directories")
+            .action(ArgAction::SetTrue),
+        Arg::new("no-ignore")
+            .long("no-ignore")
+            .help("Check files ignored by .gitignore and .ignore files")
+            .action(ArgAction::SetTrue),
+        Arg::new("mode")
+            .short('m')
+            .long("mode")
+            .help("How misspelled words are handled")
+            .value_parser(["interactive", "report", "list", "fix"])
+            .default_value("interactive"),
+        Arg::new("max-errors")
+            .long("max-errors")
+            .help("Only fail when more than this many misspellings are found")
+            .value_parser(value_parser!(usize)),
+        Arg::new("baseline")
+            .long("baseline")
+            .help("JSON file of accepted findings, which are not reported and do not fail the check")
+            .value_parser(value_parser!(PathBuf)),
+        Arg::new("update-baseline")
+            .long("update-baseline")
+            .help("Record every current finding in the baseline file instead of reporting them")
+            .action(ArgAction::SetTrue),
+        Arg::new("consistent")
+            .long("consistent")
+            .help("Report words spelled for another locale than the rest of their document, like `color` among `colour`")
+            .action(ArgAction::SetTrue),
+        Arg::new("answers")
+            .long("answers")
+            .help("In interactive mode, read the answers from this file, one per line, instead of the terminal")
+            .value_parser(value_parser!(PathBuf)),
+        Arg::new("answers-fd")
+            .long("answers-fd")
+            .help("In interactive mode, read the answers from this file descriptor instead of the terminal")
+            .conflicts_with("answers")
+            .value_parser(value_parser!(u32)),
+        Arg::new("unambiguous")
+            .long("unambiguous")
+            .help("In fix mode, only apply a suggestion when no other suggestion ties with it")
+            .action(ArgAction::SetTrue),
+        Arg::new("format")
+            .long("format")
+            .help("Output format for reported misspellings; implies --mode report unless a mode is given")
+            .value_parser(FORMATS)
+            .default_value("text"),
+        Arg::new("write")
+            .short('w')
+            .long("write")
+            .help("Rewrite the source files in place with the corrections")
+            .action(ArgAction::SetTrue),
+        Arg::new("backup")
+            .long("backup")
+            .help("With --write, keep the original file as <file>.bak")
+            .requires("write")
+            .action(ArgAction::SetTrue),
+        Arg::new("output")
+            .short('o')
+            .long("output")
+            .help("Write the corrected text to this path instead of stdout")
+            .value_parser(value_parser!(PathBuf)),
+        Arg::new("diff")
+            .long("diff")
+            .help("Print a unified diff of the corrections instead of applying them")
+            .action(ArgAction::SetTrue),
+    ]
+}
+
+/// Flags that pick which words of a document are checked, shared by `check` and `lsp`.
+fn token_args() -> Vec<Arg> {
+    vec![
+        Arg::new("syntax")
+            .long("syntax")
+            .help("How documents are parsed; auto picks the syntax from each file's extension")
+            .value_parser(["auto", "text", "markdown", "html", "xml", "latex", "rust", "python", "javascript", "go", "c", "shell"])
+            .default_value("auto"),
+        Arg::new("identifiers")
+            .long("identifiers")
+            .help("Split camelCase, snake_case and kebab-case identifiers into subwords, and check identifiers in source code")
+            .action(ArgAction::SetTrue),
+        Arg::new("abbreviations")
+            .long("abbreviations")
+            .help("Comma-separated subwords to accept in identifiers, on top of the built-in abbreviations")
+            .value_delimiter(',')
+            .action(ArgAction::Append),
+        Arg::new("html-attributes")
+            .long("html-attributes")
+            .help("Comma-separated HTML and XML attributes whose values are checked [default: alt,title,placeholder,aria-label]")
+            .value_delimiter(',')
+            .action(ArgAction::Append),
+        Arg::new("skip-tokens")
+            .long("skip-tokens")
+            .help("Comma-separated classes of tokens that are not checked, or none to check everything")
+            .value_parser(["url", "email", "path", "hash", "number", "version", "none"])
+            .value_delimiter(',')
+            .default_value("url,email,path,hash,number,version"),
+    ]
+}
+
+/// Inserts `check` where the subcommand goes if none is given, so that checking stays the default
+/// and global flags can still come first.
+fn with_default_subcommand(command: &Command, mut args: Vec<OsString>) -> Vec<OsString> {
+    let mut idx: usize = 1;
+    while let Some(arg) = args.get(idx).and_then(|arg| arg.to_str()) {
+        let Some(global) = command.get_arguments().find(|global| {
+            global.is_global_set() && global.get_long().is_some_and(|long| arg.split('=').next() == Some(format!("--{}", long).as_str()))
+        }) else {
+            break;
+        };
+        idx += match global.get_action().takes_values() && !arg.contains('=') {
+            true => 2,
+            false => 1,
+        };
+    }
+
+    let given: bool = args.get(idx).and_then(|arg| arg.to_str()).is_some_and(|arg| {
+        command.find_subcommand(arg).is_some() || ["help", "-h", "--help", "-V", "--version"].contains(&arg)
+    });
+    if !given {
+        args.insert(idx.min(args.len()), OsString::from("check"));
     }
+
+    args
 }
 
-pub fn parse_cmd_args() -> Result<String> {
-    let matches = command!()
-        .arg(
-            Arg::new("filepath")
-                .short('f')
-                .long("file")
-                .help("Path to the source file")
-                .value_parser(value_parser!(PathBuf)),
+fn format_arg(formats: &'static [&'static str]) -> Arg {
+    Arg::new("format")
+        .long("format")
+        .help("Output format")
+        .value_parser(formats.to_vec())
+        .default_value("text")
+}
+
+fn build_command() -> Command {
+    command!()
+        .args(global_args())
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("check")
+                .about("Check files, directories or piped input; the default when no subcommand is given")
+                .args(check_args())
+                .args(token_args())
+                .group(ArgGroup::new("destination").args(["write", "output", "diff"])),
+        )
+        .subcommand(
+            Command::new("serve").about("Keep the dictionary loaded and answer check, suggest and add requests on a Unix socket"),
+        )
+        .subcommand(
+            Command::new("lsp")
+                .about("Run a language server over stdio, publishing misspellings as diagnostics")
+                .args(token_args()),
         )
-        .get_matches();
+        .subcommand(
+            Command::new("suggest")
+                .about("Print the ranked suggestions for words")
+                .arg(Arg::new("words").help("Words to look up").required(true).num_args(1..))
+                .arg(format_arg(&["text", "json"])),
+        )
+        .subcommand(
+            Command::new("build")
+                .about("Build the dictionary into the BK-tree and bloom filter ahead of time")
+                .arg(
+                    Arg::new("force")
+                        .long("force")
+                        .help("Build again even if the artifacts exist")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("add")
+                .about("Add words to the personal word list")
+                .arg(Arg::new("words").help("Words to accept").required(true).num_args(1..))
+                .arg(
+                    Arg::new("list")
+                        .long("list")
+                        .help("Add to this word list instead [default: $XDG_DATA_HOME/spell_sweep/words.txt]")
+                        .value_parser(value_parser!(PathBuf)),
+                ),
+        )
+        .subcommand(
+            Command::new("stats")
+                .about("Print where the dictionary and its artifacts are and what they hold")
+                .arg(format_arg(&["text", "json"])),
+        )
+}
+
+pub fn parse_cmd_args() -> Result<Cli> {
+    let command: Command = build_command();
+    let args: Vec<OsString> = with_default_subcommand(&command, env::args_os().collect());
+    let matches: ArgMatches = command.get_matches_from(args);
 
-    let data = match matches.get_one::<PathBuf>("filepath") {
-        Some(file_path) => handle_input_data(Data::File(file_path.to_path_buf()))?,
-        None => handle_input_data(Data::Pipe(read_stdin()?))?,
+    let mut config: Config = match (matches.get_one::<PathBuf>("config"), matches.get_flag("no-config")) {
+        (Some(path), _) => Config::from_file(path)?,
+        (None, true) => Config::default(),
+        (None, false) => Config::load()?,
     };
+    for glob in config.overrides.iter() {
+        if let Some(syntax) = glob.syntax.as_deref().filter(|syntax| parse_syntax(syntax).is_none()) {
+            return Err(format!("Unknown syntax \"{}\" for {} in the configuration", syntax, glob.glob).into());
+        }
+    }
+    if let Some(dictionaries) = matches.get_many::<PathBuf>("dictionary") {
+        config.dictionaries = dictionaries.cloned().collect();
+    }
+    if let Some(cache_dir) = matches.get_one::<PathBuf>("cache-dir") {
+        config.cache_dir = Some(cache_dir.clone());
+    }
+    if matches.get_flag("no-daemon") {
+        config.daemon = false;
+    }
+    if let Some(socket) = matches.get_one::<PathBuf>("socket") {
+        config.socket = Some(socket.clone());
+    }
+    if let Some(max_edit_distance) = matches.get_one::<u8>("max-edit-distance") {
+        config.max_edit_distance = Some(*max_edit_distance);
+    }
+    if let Some(suggestions) = matches.get_one::<usize>("suggestions") {
+        config.suggestions = Some(*suggestions);
+    }
+    if let Some(locale) = matches.get_one::<String>("locale") {
+        config.locale = Some(locale.clone());
+    }
+    if let Some(locale) = config.locale.as_deref().filter(|locale| Locale::parse(locale).is_none()) {
+        return Err(format!("Unknown locale \"{}\" in the configuration; use one of {}", locale, variants::LOCALES.join(", ")).into());
+    }
+    if let Some(personal_words) = cache::personal_words().filter(|path| path.is_file()) {
+        config.words.push(personal_words);
+    }
+
+    let subcommand: Subcommand = match matches.subcommand() {
+        Some(("check", check_matches)) if check_matches.get_flag("pipe") => {
+            Subcommand::Pipe(Box::new(parse_editor_args(check_matches, &config)))
+        }
+        Some(("check", check_matches)) => Subcommand::Check(Box::new(parse_check_args(check_matches, &config)?)),
+        Some(("suggest", suggest_matches)) => Subcommand::Suggest {
+            words: suggest_matches.get_many::<String>("words").into_iter().flatten().cloned().collect(),
+            format: parse_format(suggest_matches.get_one::<String>("format").expect("format has a default value")),
+        },
+        Some(("build", build_matches)) => Subcommand::Build {
+            force: build_matches.get_flag("force"),
+        },
+        Some(("add", add_matches)) => Subcommand::Add {
+            words: add_matches.get_many::<String>("words").into_iter().flatten().cloned().collect(),
+            list: match add_matches.get_one::<PathBuf>("list") {
+                Some(list) => list.clone(),
+                None => cache::personal_words().ok_or("Could not find a data directory; set XDG_DATA_HOME or use --list")?,
+            },
+        },
+        Some(("serve", _)) => Subcommand::Serve,
+        Some(("lsp", lsp_matches)) => Subcommand::Lsp(Box::new(parse_editor_args(lsp_matches, &config))),
+        Some(("stats", stats_matches)) => Subcommand::Stats {
+            format: parse_format(stats_matches.get_one::<String>("format").expect("format has a default value")),
+        },
+        _ => unreachable!("a subcommand is required"),
+    };
+
+    Ok(Cli {
+        config,
+        lang: matches.get_one::<String>("lang").cloned(),
+        subcommand,
+    })
+}
+
+fn parse_check_args(matches: &ArgMatches, config: &Config) -> Result<CmdData> {
+    let paths: Vec<PathBuf> = matches
+        .get_many::<PathBuf>("filepath")
+        .into_iter()
+        .flatten()
+        .chain(matches.get_many::<PathBuf>("paths").into_iter().flatten())
+        .cloned()
+        .collect();
+
+    // Globs given on the command line replace the ones from the configuration.
+    let walk_options = WalkOptions::new(
+        match matches.get_many::<String>("include") {
+            Some(includes) => includes.cloned().collect(),
+            None => config.files.clone(),
+        },
+        match matches.get_many::<String>("exclude") {
+            Some(excludes) => excludes.cloned().collect(),
+            None => config.ignore.clone(),
+        },
+        matches.get_flag("hidden"),
+        matches.get_flag("no-ignore"),
+    );
+    let collected: Collected = match paths.is_empty() {
+        true => Collected::default(),
+        false => walk::collect_files(&paths, &walk_options)?,
+    };
+    for err in collected.errors.iter() {
+        eprintln!("Error: {}", err);
+    }
+    let inputs: Vec<Input> = match paths.is_empty() {
+        true => vec![stdin_input()?],
+        false => collected.files.into_iter().map(Input::File).collect(),
+    };
+    let format_name: &str = match (matches.value_source("format"), config.format.as_deref()) {
+        (Some(ValueSource::DefaultValue), Some(format_name)) => format_name,
+        _ => matches.get_one::<String>("format").expect("format has a default value"),
+    };
+    if !FORMATS.contains(&format_name) {
+        return Err(format!("Unknown format \"{}\" in the configuration", format_name).into());
+    }
+    let format = parse_format(format_name);
+    let mode = match (matches.value_source("mode"), format) {
+        (Some(ValueSource::DefaultValue), format) if format != Format::Text => Mode::Report,
+        _ => parse_mode(
+            matches.get_one::<String>("mode").expect("mode has a default value"),
+            matches.get_flag("unambiguous"),
+        ),
+    };
+
+    let destination = if matches.get_flag("write") {
+        Destination::InPlace {
+            backup: matches.get_flag("backup"),
+        }
+    } else if matches.get_flag("diff") {
+        Destination::Diff
+    } else {
+        match matches.get_one::<PathBuf>("output") {
+            Some(path) => Destination::File(path.to_path_buf()),
+            None => Destination::Stdout,
+        }
+    };
+
+    if matches!(destination, Destination::InPlace { .. }) && inputs.contains(&Input::Stdin) {
+        return Err("--write needs files to rewrite; piped input can only be written to stdout or --output".into());
+    }
+    if matches!(destination, Destination::File(_)) && inputs.len() > 1 {
+        return Err("--output can only be used with a single input file".into());
+    }
+    // Writing to the input would truncate it before it is read; --write replaces it safely.
+    if let (Destination::File(output), [Input::File(input)]) = (&destination, inputs.as_slice()) {
+        if fs::canonicalize(output).is_ok_and(|output| fs::canonicalize(input).is_ok_and(|input| input == output)) {
+            return Err(format!("--output {} is the input file; use --write to correct it in place", output.display()).into());
+        }
+    }
+
+    let mut config: Config = config.clone();
+    if let Some(baseline) = matches.get_one::<PathBuf>("baseline") {
+        config.baseline = Some(baseline.clone());
+    }
+    if let Some(max_errors) = matches.get_one::<usize>("max-errors") {
+        config.max_errors = *max_errors;
+    }
+    if matches.get_flag("consistent") {
+        config.consistent = true;
+    }
+    let update_baseline: bool = matches.get_flag("update-baseline");
+    let answers: Option<PathBuf> = match (matches.get_one::<PathBuf>("answers"), matches.get_one::<u32>("answers-fd")) {
+        (Some(path), _) => Some(path.clone()),
+        (None, Some(fd)) => Some(PathBuf::from(format!("/dev/fd/{}", fd))),
+        (None, None) => None,
+    };
+    // Without a terminal there is nobody to answer the prompts, so the misspellings are reported.
+    let mode: Mode = match mode {
+        Mode::Interactive if answers.is_none() && !prompt::has_terminal() => {
+            eprintln!("No terminal to prompt on; reporting misspelled words instead");
+            Mode::Report
+        }
+        mode => mode,
+    };
+    if update_baseline && config.baseline.is_none() {
+        return Err("--update-baseline needs a baseline file; use --baseline or set baseline in the configuration".into());
+    }
+
+    Ok(CmdData {
+        inputs,
+        mode,
+        format,
+        destination,
+        syntax: parse_syntax(matches.get_one::<String>("syntax").expect("syntax has a default value")),
+        syntax_options: parse_syntax_options(matches),
+        abbreviations: parse_abbreviations(matches),
+        config,
+        update_baseline,
+        answers,
+        walk_failed: !collected.errors.is_empty(),
+    })
+}
+
+/// Settings for text an editor hands over, rather than inputs read from disk.
+fn parse_editor_args(matches: &ArgMatches, config: &Config) -> CmdData {
+    CmdData {
+        syntax: parse_syntax(matches.get_one::<String>("syntax").expect("syntax has a default value")),
+        syntax_options: parse_syntax_options(matches),
+        abbreviations: parse_abbreviations(matches),
+        config: config.clone(),
+        ..Default::default()
+    }
+}
 
-    Ok(data)
+fn parse_syntax_options(matches: &ArgMatches) -> syntax::Options {
+    syntax::Options {
+        identifiers: matches.get_flag("identifiers"),
+        attributes: match matches.get_many::<String>("html-attributes") {
+            Some(attributes) => attributes.cloned().collect(),
+            None => syntax::Options::default().attributes,
+        },
+        skipped_tokens: matches
+            .get_many::<String>("skip-tokens")
+            .into_iter()
+            .flatten()
+            .filter_map(|name| TokenClass::from_name(name))
+            .collect(),
+    }
+}
+
+fn parse_abbreviations(matches: &ArgMatches) -> HashSet<String> {
+    processor::ABBREVIATIONS
+        .iter()
+        .map(|abbreviation| abbreviation.to_string())
+        .chain(
+            matches
+                .get_many::<String>("abbreviations")
+                .into_iter()
+                .flatten()
+                .map(|abbreviation| abbreviation.to_lowercase()),
+        )
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use std::process::Command;
 
-    // use super::*;
+    use std::ffi::OsString;
+
+    use super::{build_command, parse_format, parse_mode, parse_syntax, with_default_subcommand};
+    use crate::{
+        output::Format,
+        spell_check::Mode,
+        syntax::{source::Language, Syntax},
+    };
+
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!(parse_mode("interactive", false), Mode::Interactive);
+        assert_eq!(parse_mode("report", false), Mode::Report);
+        assert_eq!(parse_mode("list", true), Mode::List);
+        assert_eq!(parse_mode("fix", true), Mode::Fix { unambiguous: true });
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!(parse_format("text"), Format::Text);
+        assert_eq!(parse_format("sarif"), Format::Sarif);
+        assert_eq!(parse_format("checkstyle"), Format::Checkstyle);
+    }
+
+    #[test]
+    fn test_parse_syntax() {
+        assert_eq!(parse_syntax("auto"), None);
+        assert_eq!(parse_syntax("markdown"), Some(Syntax::Markdown));
+        assert_eq!(parse_syntax("python"), Some(Syntax::Source(Language::Python)));
+        assert_eq!(parse_syntax("xml"), Some(Syntax::Html));
+    }
+
+    #[test]
+    fn test_default_subcommand() {
+        let args = |args: &[&str]| -> Vec<OsString> { args.iter().map(OsString::from).collect() };
+        let complete = |given: &[&str]| with_default_subcommand(&build_command(), args(given));
+
+        assert_eq!(complete(&["spell_sweep"]), args(&["spell_sweep", "check"]));
+        assert_eq!(complete(&["spell_sweep", "-f", "add"]), args(&["spell_sweep", "check", "-f", "add"]));
+        assert_eq!(
+            complete(&["spell_sweep", "--dictionary", "en.txt", "--no-daemon", "add", "foo"]),
+            args(&["spell_sweep", "--dictionary", "en.txt", "--no-daemon", "add", "foo"])
+        );
+        assert_eq!(
+            complete(&["spell_sweep", "--dictionary=en.txt", "notes.txt"]),
+            args(&["spell_sweep", "--dictionary=en.txt", "check", "notes.txt"])
+        );
+        assert_eq!(complete(&["spell_sweep", "--help"]), args(&["spell_sweep", "--help"]));
+    }
+
     #[test]
     fn file_not_found() {
         let output = Command::new("cargo")
@@ -94,6 +788,42 @@ mod tests {
             .output()
             .expect("Failed to execute command");
 
-        assert_eq!(output.status.success(), false);
+        assert!(!output.status.success());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unreadable_directory() {
+        use std::{env, fs, os::unix::fs::PermissionsExt, path::PathBuf};
+
+        let root: PathBuf = env::temp_dir().join("spell_sweep_unreadable_test");
+        let locked: PathBuf = root.join("docs/locked");
+        let _ = fs::set_permissions(&locked, fs::Permissions::from_mode(0o755));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&locked).expect("Failed to create directories");
+        fs::write(root.join("words.txt"), "hello\nthere\n").expect("Failed to write dictionary");
+        fs::write(root.join("docs/notes.txt"), "Helo there\n").expect("Failed to write test file");
+        fs::write(locked.join("secret.txt"), "Hello there\n").expect("Failed to write test file");
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).expect("Failed to set permissions");
+
+        // Permissions do not keep root out, in which case there is no error to report.
+        if fs::read_dir(&locked).is_err() {
+            let output = Command::new("cargo")
+                .args(["run", "--", "--no-daemon", "--dictionary"])
+                .arg(root.join("words.txt"))
+                .arg("--cache-dir")
+                .arg(root.join("cache"))
+                .args(["check", "--mode", "report"])
+                .arg(root.join("docs"))
+                .output()
+                .expect("Failed to execute command");
+
+            assert_eq!(output.status.code(), Some(2));
+            assert!(String::from_utf8_lossy(&output.stdout).contains("Helo"));
+            assert!(String::from_utf8_lossy(&output.stderr).contains(&locked.display().to_string()));
+        }
+
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).expect("Failed to set permissions");
+        fs::remove_dir_all(&root).expect("Failed to remove test directory");
     }
 }
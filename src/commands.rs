@@ -0,0 +1,326 @@
+use std::{
+    collections::HashSet,
+    error::Error,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+
+use crate::{
+    bk_tree::BKTree,
+    bloom_filter::BloomFilter,
+    cache::{self, Artifacts},
+    cmd::{EXIT_CLEAN, EXIT_MISSPELLED},
+    config::Config,
+    daemon::Client,
+    dictionary::Dictionary,
+    output::Format,
+    processor,
+    spell_check::{self, SpellCheck},
+    variants::Locale,
+};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// Looks words up through the daemon serving the configured dictionary if one is running, and
+/// otherwise loads the dictionary into this process.
+pub fn load_spell_check(config: &Config) -> Result<SpellCheck> {
+    if config.daemon {
+        let dictionaries: Vec<PathBuf> = cache::find_dictionaries(config)?;
+        if let Some(client) = cache::socket_path(config, &dictionaries).and_then(|socket| Client::connect(&socket)) {
+            let local_config: Config = config.clone();
+            return Ok(SpellCheck::with_daemon(client, move || load_local_spell_check(&local_config))
+                .with_words(config.read_words()?)
+                .with_limits(config.max_edit_distance, config.suggestions)
+                .with_locale(config.locale.as_deref().and_then(Locale::parse)));
+        }
+    }
+
+    load_local_spell_check(config)
+}
+
+/// Loads the dictionary the configuration points at, building and caching its artifacts first if
+/// they do not exist yet.
+pub fn load_local_spell_check(config: &Config) -> Result<SpellCheck> {
+    let extra_words: Vec<String> = config.read_words()?;
+    let dictionaries: Vec<PathBuf> = cache::find_dictionaries(config)?;
+    let artifacts: Artifacts = Artifacts::locate(config, &dictionaries)?;
+    if !artifacts.exist() {
+        eprintln!("Building the dictionary into {}", artifacts.bk_tree.parent().unwrap_or(Path::new(".")).display());
+        artifacts.create_dirs()?;
+    }
+
+    Ok(SpellCheck::new(
+        &artifacts.bk_tree.to_string_lossy(),
+        &artifacts.bloom_filter.to_string_lossy(),
+        &dictionaries,
+        config.alphabet_length,
+        config.false_positive_rate,
+    )?
+    .with_words(extra_words)
+    .with_limits(config.max_edit_distance, config.suggestions)
+    .with_locale(config.locale.as_deref().and_then(Locale::parse)))
+}
+
+#[derive(Serialize)]
+struct Suggestion {
+    word: String,
+    distance: u8,
+}
+
+#[derive(Serialize)]
+struct Lookup<'a> {
+    word: &'a str,
+    correct: bool,
+    suggestions: Vec<Suggestion>,
+}
+
+/// Prints whether each of `words` is correct and the ranked suggestions for the ones that are
+/// not. Exits with `EXIT_MISSPELLED` if any word is misspelled.
+pub fn suggest(spell_check: &SpellCheck, words: &[String], format: Format) -> Result<i32> {
+    let lookups: Vec<Lookup> = words
+        .iter()
+        .map(|word| {
+            let correct: bool = spell_check.is_correct(word);
+            let suggestions: Vec<Suggestion> = match correct {
+                true => Vec::new(),
+                false => spell_check
+                    .suggest(word)
+                    .into_iter()
+                    .map(|(suggestion, distance)| Suggestion {
+                        word: spell_check::convert_case(&suggestion, word),
+                        distance,
+                    })
+                    .collect(),
+            };
+            Lookup { word, correct, suggestions }
+        })
+        .collect();
+
+    let mut stdout = io::stdout().lock();
+    match format {
+        Format::Json => writeln!(stdout, "{}", serde_json::to_string_pretty(&lookups)?)?,
+        _ => {
+            for lookup in lookups.iter() {
+                let suggestions: Vec<&str> = lookup.suggestions.iter().map(|suggestion| suggestion.word.as_str()).collect();
+                match lookup.correct {
+                    true => writeln!(stdout, "{}: correct", lookup.word)?,
+                    false => writeln!(stdout, "{}: {}", lookup.word, suggestions.join(", "))?,
+                }
+            }
+        }
+    }
+    stdout.flush()?;
+
+    Ok(match lookups.iter().all(|lookup| lookup.correct) {
+        true => EXIT_CLEAN,
+        false => EXIT_MISSPELLED,
+    })
+}
+
+/// Builds the artifacts of the configured dictionary, unless they exist and `force` is not set.
+pub fn build(config: &Config, force: bool) -> Result<i32> {
+    let dictionaries: Vec<PathBuf> = cache::find_dictionaries(config)?;
+    let artifacts: Artifacts = Artifacts::locate(config, &dictionaries)?;
+
+    if artifacts.exist() && !force {
+        println!("Up to date: {}, {}", artifacts.bk_tree.display(), artifacts.bloom_filter.display());
+        return Ok(EXIT_CLEAN);
+    }
+
+    let dictionary: Dictionary = cache::load_dictionary(&dictionaries, config.alphabet_length)?;
+    artifacts.build(&dictionary, config.false_positive_rate)?;
+    println!(
+        "Built {} words into {}, {}",
+        dictionary.words.len(),
+        artifacts.bk_tree.display(),
+        artifacts.bloom_filter.display()
+    );
+
+    Ok(EXIT_CLEAN)
+}
+
+/// Appends the words of `words` that are not in the word list at `list` yet, creating it if
+/// needed, and returns the words that were added.
+pub fn append_words<'a>(words: &'a [String], list: &Path) -> Result<Vec<&'a str>> {
+    let contents: String = match fs::read_to_string(list) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(format!("{}: {}", list.display(), err).into()),
+    };
+    let normalize = |word: &str| processor::normalize_apostrophes(&word.trim().to_lowercase());
+    let mut known: HashSet<String> = contents.lines().map(normalize).collect();
+    let added: Vec<&str> = words
+        .iter()
+        .map(|word| word.trim())
+        .filter(|word| !word.is_empty() && known.insert(normalize(word)))
+        .collect();
+
+    if let Some(parent) = list.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(list)
+        .map_err(|err| format!("{}: {}", list.display(), err))?;
+    if !contents.is_empty() && !contents.ends_with('\n') {
+        writeln!(file)?;
+    }
+    for word in added.iter() {
+        writeln!(file, "{}", word)?;
+    }
+
+    Ok(added)
+}
+
+/// Adds `words` to the word list at `list`, through the daemon if one is running so that it
+/// accepts them right away.
+pub fn add(config: &Config, words: &[String], list: &Path) -> Result<i32> {
+    let client: Option<Client> = match config.daemon {
+        true => cache::socket_path(config, &cache::find_dictionaries(config)?).and_then(|socket| Client::connect(&socket)),
+        false => None,
+    };
+    let added: usize = match client {
+        // The daemon runs in a directory of its own, so it is sent where the list really is.
+        Some(client) => client.add(words, Some(&std::path::absolute(list)?))?.len(),
+        None => append_words(words, list)?.len(),
+    };
+    println!("Added {} word(s) to {}", added, list.display());
+
+    Ok(EXIT_CLEAN)
+}
+
+#[derive(Serialize)]
+struct BKTreeStats {
+    path: PathBuf,
+    bytes: Option<u64>,
+    words: Option<u32>,
+    max_word_length: Option<u16>,
+}
+
+#[derive(Serialize)]
+struct BloomFilterStats {
+    path: PathBuf,
+    bytes: Option<u64>,
+    bits: Option<u64>,
+    hash_count: Option<u32>,
+    false_positive_rate: Option<f32>,
+}
+
+#[derive(Serialize)]
+struct Stats {
+    dictionaries: Vec<PathBuf>,
+    words: usize,
+    word_lists: Vec<PathBuf>,
+    bk_tree: BKTreeStats,
+    bloom_filter: BloomFilterStats,
+}
+
+/// Prints where the dictionary and its artifacts are and what they hold, without building them.
+pub fn stats(config: &Config, format: Format) -> Result<i32> {
+    let dictionaries: Vec<PathBuf> = cache::find_dictionaries(config)?;
+    let artifacts: Artifacts = Artifacts::locate(config, &dictionaries)?;
+    let file_size = |path: &Path| fs::metadata(path).ok().map(|metadata| metadata.len());
+
+    // The counts are read from the built tree, so only a dictionary that was never built is loaded.
+    let bk_tree: Option<(u32, u16)> = match artifacts.bk_tree.exists() {
+        true => Some(BKTree::counts_from_file(&artifacts.bk_tree.to_string_lossy())?),
+        false => None,
+    };
+    let words: usize = match bk_tree {
+        Some((size, _)) => size as usize,
+        None => cache::load_dictionary(&dictionaries, config.alphabet_length)?.words.len(),
+    };
+    let bloom_filter: Option<BloomFilter> = match artifacts.bloom_filter.exists() {
+        true => Some(BloomFilter::from_file(&artifacts.bloom_filter.to_string_lossy())?),
+        false => None,
+    };
+    let stats: Stats = Stats {
+        dictionaries,
+        words,
+        word_lists: config.words.clone(),
+        bk_tree: BKTreeStats {
+            bytes: file_size(&artifacts.bk_tree),
+            words: bk_tree.map(|(size, _)| size),
+            max_word_length: bk_tree.map(|(_, max_word_length)| max_word_length),
+            path: artifacts.bk_tree.clone(),
+        },
+        bloom_filter: BloomFilterStats {
+            bytes: file_size(&artifacts.bloom_filter),
+            bits: bloom_filter.as_ref().map(|filter| filter.size),
+            hash_count: bloom_filter.as_ref().map(|filter| filter.hash_count),
+            false_positive_rate: bloom_filter.as_ref().map(|filter| filter.fp_prob),
+            path: artifacts.bloom_filter.clone(),
+        },
+    };
+
+    let mut stdout = io::stdout().lock();
+    match format {
+        Format::Json => writeln!(stdout, "{}", serde_json::to_string_pretty(&stats)?)?,
+        _ => stdout.write_all(render_stats(&stats).as_bytes())?,
+    }
+    stdout.flush()?;
+
+    Ok(EXIT_CLEAN)
+}
+
+fn render_stats(stats: &Stats) -> String {
+    let paths = |paths: &[PathBuf]| match paths.is_empty() {
+        true => "none".to_string(),
+        false => paths.iter().map(|path| path.display().to_string()).collect::<Vec<String>>().join(", "),
+    };
+    let bk_tree: String = match (stats.bk_tree.bytes, stats.bk_tree.words, stats.bk_tree.max_word_length) {
+        (Some(bytes), Some(words), Some(max_word_length)) => {
+            format!("{} bytes, {} words, longest word {} bytes", bytes, words, max_word_length)
+        }
+        _ => "not built".to_string(),
+    };
+    let bloom_filter: String = match (
+        stats.bloom_filter.bytes,
+        stats.bloom_filter.bits,
+        stats.bloom_filter.hash_count,
+        stats.bloom_filter.false_positive_rate,
+    ) {
+        (Some(bytes), Some(bits), Some(hash_count), Some(fp_prob)) => format!(
+            "{} bytes, {} bits, {} hashes, false positive rate {}",
+            bytes, bits, hash_count, fp_prob
+        ),
+        _ => "not built".to_string(),
+    };
+
+    format!(
+        "dictionaries: {}\nwords: {}\nword lists: {}\nbk-tree: {}\n  {}\nbloom filter: {}\n  {}\n",
+        paths(&stats.dictionaries),
+        stats.words,
+        paths(&stats.word_lists),
+        stats.bk_tree.path.display(),
+        bk_tree,
+        stats.bloom_filter.path.display(),
+        bloom_filter
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf};
+
+    use super::append_words;
+
+    #[test]
+    fn test_append_words() {
+        let root: PathBuf = env::temp_dir().join("spell_sweep_add_test");
+        let _ = fs::remove_dir_all(&root);
+        let list: PathBuf = root.join("nested/words.txt");
+
+        let words: Vec<String> = vec!["Rkyv".to_string(), "serde".to_string()];
+        assert_eq!(append_words(&words, &list).unwrap(), vec!["Rkyv", "serde"]);
+        let words: Vec<String> = vec!["rkyv".to_string(), "tokio".to_string(), "tokio".to_string()];
+        assert_eq!(append_words(&words, &list).unwrap(), vec!["tokio"]);
+        assert_eq!(fs::read_to_string(&list).unwrap(), "Rkyv\nserde\ntokio\n");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,402 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    error::Error,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use globset::{Glob, GlobMatcher};
+use serde::Deserialize;
+
+use crate::cache;
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// The name of the project configuration file, looked up from the working directory upwards.
+pub const FILE_NAME: &str = ".spellsweep.toml";
+
+/// Settings read from `.spellsweep.toml`. Every field is optional; command line flags take
+/// precedence over the values set here. Relative paths are resolved against the directory
+/// holding the file.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// Word lists the dictionary is built from, one word per line. Lists that are not found are
+    /// looked up in the data directories; `dictionary.txt` is used if there are none.
+    pub dictionaries: Vec<PathBuf>,
+    /// Word lists whose words are accepted on top of the dictionary.
+    pub words: Vec<PathBuf>,
+    /// Globs of files to skip when walking directories.
+    pub ignore: Vec<String>,
+    /// Globs of files to check when walking directories; every file is checked if empty.
+    pub files: Vec<String>,
+    /// Largest edit distance a suggestion may be from the misspelled word.
+    pub max_edit_distance: Option<u8>,
+    /// Largest number of suggestions given for a misspelled word.
+    pub suggestions: Option<usize>,
+    /// Output format of reports, as accepted by `--format`.
+    pub format: Option<String>,
+    /// Number of distinct characters the BK-tree is built for.
+    pub alphabet_length: u16,
+    /// False positive rate the bloom filter is sized for.
+    pub false_positive_rate: f32,
+    /// Directory the built artifacts are cached in, keyed by a hash of the dictionaries.
+    pub cache_dir: Option<PathBuf>,
+    /// Where the built BK-tree is stored, instead of the cache directory.
+    pub bk_tree: Option<PathBuf>,
+    /// Where the built bloom filter is stored, instead of the cache directory.
+    pub bloom_filter: Option<PathBuf>,
+    /// Whether checks go through a running `spell_sweep serve` daemon when there is one.
+    pub daemon: bool,
+    /// The socket the daemon listens on, instead of one picked for the dictionaries.
+    pub socket: Option<PathBuf>,
+    /// Findings accepted for now, which do not fail a check; written by `--update-baseline`.
+    pub baseline: Option<PathBuf>,
+    /// Largest number of findings a check may report and still pass.
+    pub max_errors: usize,
+    /// The language of `dictionaries`, which documents are checked in unless `--lang`, an
+    /// override or the document itself picks another.
+    pub lang: Option<String>,
+    /// Dictionaries for other languages, by language name like `de` or `es-MX`.
+    pub languages: BTreeMap<String, LanguageProfile>,
+    /// The locale words are held to, like `en-GB`, so `color` is misspelled and `colour` suggested.
+    pub locale: Option<String>,
+    /// Whether words spelled for another locale than the rest of their document are reported.
+    pub consistent: bool,
+    /// Syntaxes and languages for files matching a glob, tried in order.
+    pub overrides: Vec<Override>,
+}
+
+/// The dictionaries of one language. The word lists are accepted on top of the `words` of the
+/// configuration, which hold words of every language, like names.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct LanguageProfile {
+    pub dictionaries: Vec<PathBuf>,
+    pub words: Vec<PathBuf>,
+}
+
+/// Reads files matching `glob` with another syntax, as accepted by `--syntax`, or in another
+/// language.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Override {
+    pub glob: String,
+    pub syntax: Option<String>,
+    pub lang: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            dictionaries: Vec::new(),
+            words: Vec::new(),
+            ignore: Vec::new(),
+            files: Vec::new(),
+            max_edit_distance: None,
+            suggestions: None,
+            format: None,
+            alphabet_length: 255,
+            false_positive_rate: 0.01,
+            cache_dir: None,
+            bk_tree: None,
+            bloom_filter: None,
+            daemon: true,
+            socket: None,
+            baseline: None,
+            max_errors: 0,
+            lang: None,
+            languages: BTreeMap::new(),
+            locale: None,
+            consistent: false,
+            overrides: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Finds the closest `.spellsweep.toml` in `start` or one of its ancestors.
+    pub fn discover(start: &Path) -> Option<PathBuf> {
+        start
+            .ancestors()
+            .map(|directory| directory.join(FILE_NAME))
+            .find(|path| path.is_file())
+    }
+
+    /// Loads the configuration file at `path`, resolving its relative paths against its directory.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents: String = fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        let mut config: Config = toml::from_str(&contents).map_err(|err| format!("{}: {}", path.display(), err))?;
+        let base: &Path = path.parent().unwrap_or(Path::new("."));
+
+        // Dictionaries missing next to the file keep their name, so they can be found in the data directories.
+        let dictionaries = config
+            .dictionaries
+            .iter_mut()
+            .chain(config.languages.values_mut().flat_map(|profile| profile.dictionaries.iter_mut()));
+        for path in dictionaries.filter(|path| base.join(&**path).exists()) {
+            *path = base.join(&*path);
+        }
+        for path in config
+            .words
+            .iter_mut()
+            .chain(config.languages.values_mut().flat_map(|profile| profile.words.iter_mut()))
+            .chain(config.cache_dir.iter_mut())
+            .chain(config.bk_tree.iter_mut())
+            .chain(config.bloom_filter.iter_mut())
+            .chain(config.socket.iter_mut())
+            .chain(config.baseline.iter_mut())
+        {
+            *path = base.join(&*path);
+        }
+        let glob_base: PathBuf = absolute(match base.as_os_str().is_empty() {
+            true => Path::new("."),
+            false => base,
+        });
+        for glob in config.overrides.iter_mut() {
+            glob.glob = resolve_glob(&glob_base, &glob.glob);
+        }
+
+        Ok(config)
+    }
+
+    /// Loads the configuration file found from the working directory, or the defaults if there is none.
+    pub fn load() -> Result<Self> {
+        match Self::discover(&env::current_dir()?) {
+            Some(path) => Self::from_file(&path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Returns the syntax name of the first override with a syntax whose glob matches `path`.
+    pub fn syntax_override(&self, path: &Path) -> Option<&str> {
+        self.find_override(path, |glob| glob.syntax.as_deref())
+    }
+
+    /// Returns the language of the first override with a language whose glob matches `path`.
+    pub fn language_override(&self, path: &Path) -> Option<&str> {
+        self.find_override(path, |glob| glob.lang.as_deref())
+    }
+
+    fn find_override<'a, F>(&'a self, path: &Path, value: F) -> Option<&'a str>
+    where
+        F: Fn(&'a Override) -> Option<&'a str>,
+    {
+        let path: PathBuf = absolute(path);
+
+        self.overrides
+            .iter()
+            .filter(|glob| matcher(&glob.glob).is_some_and(|matcher| matcher.is_match(&path)))
+            .find_map(value)
+    }
+
+    /// The configuration for checking text in the language `name`, looked up in this order: its
+    /// profile, the `lang` of the configuration when it is the same primary language, a
+    /// dictionary named after it in the data directories like `de_DE.dic`, and a profile of the
+    /// same primary language, so that `en-US` is checked as `en` and `de` serves `de-AT`. No
+    /// language at all gives the configuration as it is.
+    pub fn for_language(&self, name: Option<&str>) -> Result<Self> {
+        self.for_language_in(name, cache::env_var)
+    }
+
+    /// Like [`Config::for_language`], finding the data directories through `var`.
+    fn for_language_in<F: Fn(&str) -> Option<String>>(&self, name: Option<&str>, var: F) -> Result<Self> {
+        let Some(name) = name.filter(|name| !self.lang.as_deref().is_some_and(|lang| same_language(lang, name))) else {
+            return Ok(self.clone());
+        };
+        let with_dictionaries = |dictionaries: Vec<PathBuf>, words: &[PathBuf]| {
+            let mut config: Config = self.clone();
+            config.lang = Some(name.to_string());
+            config.dictionaries = dictionaries;
+            config.words.extend(words.iter().cloned());
+            config
+        };
+
+        if let Some((_, profile)) = self.languages.iter().find(|(profile, _)| same_language(profile, name)) {
+            return Ok(with_dictionaries(profile.dictionaries.clone(), &profile.words));
+        }
+        if self.lang.as_deref().is_some_and(|lang| primary_language(lang) == primary_language(name)) {
+            return Ok(self.clone());
+        }
+        if let Some(dictionary) = cache::find_language_dictionary(name, var) {
+            return Ok(with_dictionaries(vec![dictionary], &[]));
+        }
+        match self.languages.iter().find(|(profile, _)| primary_language(profile) == primary_language(name)) {
+            Some((_, profile)) => Ok(with_dictionaries(profile.dictionaries.clone(), &profile.words)),
+            None => Err(format!(
+                "No language profile for {} in the configuration, and no {}.dic or {}.txt dictionary",
+                name, name, name
+            )
+            .into()),
+        }
+    }
+
+    /// Reads the extra word lists.
+    pub fn read_words(&self) -> Result<Vec<String>> {
+        let mut words: Vec<String> = Vec::new();
+        for path in self.words.iter() {
+            let contents: String = fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+            words.extend(contents.lines().map(str::trim).filter(|word| !word.is_empty()).map(str::to_string));
+        }
+
+        Ok(words)
+    }
+}
+
+/// Spells a language name one way, like `en-gb` for `en_GB`.
+pub fn normalize_language(name: &str) -> String {
+    name.trim().to_lowercase().replace('_', "-")
+}
+
+/// The language of `name` without its region or script, like `de` for `de_AT`.
+fn primary_language(name: &str) -> String {
+    normalize_language(name).split('-').next().unwrap_or("").to_string()
+}
+
+/// Whether `a` and `b` name the same language, like `en_GB` and `en-gb`.
+pub fn same_language(a: &str, b: &str) -> bool {
+    normalize_language(a) == normalize_language(b)
+}
+
+/// Anchors a glob from the configuration file at `base`. Globs without a `/` match a file name
+/// anywhere below it, like in `.gitignore`.
+fn resolve_glob(base: &Path, glob: &str) -> String {
+    let base: String = base.display().to_string();
+    match glob.contains('/') {
+        true => format!("{}/{}", base, glob.trim_start_matches("./")),
+        false => format!("{}/**/{}", base, glob),
+    }
+}
+
+/// Makes `path` absolute the same way however it is spelled, so that `./docs/a.md`, `docs/a.md`
+/// and `docs/../docs/a.md` are all one path. Paths that do not exist lose their `.` and `..`
+/// components instead of having their symbolic links resolved.
+fn absolute(path: &Path) -> PathBuf {
+    if let Ok(path) = fs::canonicalize(path) {
+        return path;
+    }
+
+    let path: PathBuf = env::current_dir().map_or(path.to_path_buf(), |cwd| cwd.join(path));
+    let mut normal: PathBuf = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normal.pop();
+            }
+            component => normal.push(component),
+        }
+    }
+
+    normal
+}
+
+fn matcher(glob: &str) -> Option<GlobMatcher> {
+    Glob::new(glob).ok().map(|glob| glob.compile_matcher())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env, fs,
+        path::{Path, PathBuf},
+    };
+
+    use super::{Config, FILE_NAME};
+
+    #[test]
+    fn test_from_file() {
+        let root: PathBuf = env::temp_dir().join("spell_sweep_config_test");
+        let _ = fs::remove_dir_all(&root);
+        let nested: PathBuf = root.join("docs/guide");
+        fs::create_dir_all(&nested).expect("Failed to create directories");
+        fs::create_dir_all(root.join("words")).expect("Failed to create directories");
+        fs::write(root.join("words/en.txt"), "hello\n").expect("Failed to write dictionary");
+        fs::write(root.join("words/de.txt"), "hallo\n").expect("Failed to write dictionary");
+        fs::write(
+            root.join(FILE_NAME),
+            "dictionaries = [\"words/en.txt\", \"en_GB.txt\"]\n\
+             lang = \"en\"\n\
+             locale = \"en-GB\"\n\
+             consistent = true\n\
+             cache-dir = \".cache\"\n\
+             baseline = \"spelling-baseline.json\"\n\
+             max-errors = 3\n\
+             max-edit-distance = 2\n\
+             format = \"json\"\n\
+             [[overrides]]\n\
+             glob = \"*.txt\"\n\
+             syntax = \"markdown\"\n\
+             [[overrides]]\n\
+             glob = \"docs/de/**\"\n\
+             lang = \"de\"\n\
+             [languages.de]\n\
+             dictionaries = [\"words/de.txt\"]\n\
+             words = [\"words/names.txt\"]\n",
+        )
+        .expect("Failed to write config");
+
+        let path = Config::discover(&nested).expect("Config should be found from a nested directory");
+        assert_eq!(path, root.join(FILE_NAME));
+
+        let config: Config = Config::from_file(&path).expect("Config should parse");
+        assert_eq!(config.dictionaries, vec![root.join("words/en.txt"), PathBuf::from("en_GB.txt")]);
+        assert_eq!(config.cache_dir, Some(root.join(".cache")));
+        assert_eq!(config.bk_tree, None);
+        assert_eq!(config.baseline, Some(root.join("spelling-baseline.json")));
+        assert_eq!(config.max_errors, 3);
+        assert_eq!(config.locale.as_deref(), Some("en-GB"));
+        assert!(config.consistent);
+        assert_eq!(config.max_edit_distance, Some(2));
+        assert_eq!(config.format.as_deref(), Some("json"));
+        assert_eq!(config.alphabet_length, 255);
+        assert_eq!(config.syntax_override(&nested.join("notes.txt")), Some("markdown"));
+        assert_eq!(config.syntax_override(Path::new("/elsewhere/notes.txt")), None);
+        assert_eq!(config.syntax_override(&nested.join("notes.md")), None);
+        assert_eq!(config.syntax_override(&root.join("docs/de/notes.md")), None);
+        assert_eq!(config.language_override(&root.join("docs/de/notes.md")), Some("de"));
+        assert_eq!(config.language_override(&nested.join("notes.txt")), None);
+        assert_eq!(config.language_override(&root.join("./docs/de/notes.md")), Some("de"));
+        assert_eq!(config.language_override(&nested.join("../../docs/de/notes.md")), Some("de"));
+
+        // Globs are anchored however the path of the configuration is spelled.
+        let config_via: Config = Config::from_file(&nested.join("../../").join(FILE_NAME)).expect("Config should parse");
+        assert_eq!(config_via.language_override(&root.join("docs/de/notes.md")), Some("de"));
+        assert_eq!(config_via.syntax_override(&root.join("./notes.txt")), Some("markdown"));
+
+        // Dictionaries are looked up in a data directory of the test's own, whatever the host has.
+        let data_home: PathBuf = root.join("data");
+        fs::create_dir_all(data_home.join("spell_sweep")).expect("Failed to create directories");
+        fs::write(data_home.join("spell_sweep/en_US.dic"), "1\ncolor\n").expect("Failed to write dictionary");
+        fs::write(data_home.join("spell_sweep/fr.txt"), "bonjour\n").expect("Failed to write dictionary");
+        let var = |name: &str| match name {
+            "XDG_DATA_HOME" => Some(data_home.display().to_string()),
+            _ => Some(String::new()),
+        };
+        let for_language = |name: Option<&str>| config.for_language_in(name, var);
+
+        assert_eq!(for_language(Some("en_US")).unwrap(), config);
+        assert_eq!(for_language(None).unwrap(), config);
+        let german: Config = for_language(Some("de-AT")).expect("The profile of de should serve de-AT");
+        assert_eq!(german.lang.as_deref(), Some("de-AT"));
+        assert_eq!(german.dictionaries, vec![root.join("words/de.txt")]);
+        assert_eq!(german.words, vec![root.join("words/names.txt")]);
+        assert_eq!(for_language(Some("DE")).unwrap().dictionaries, vec![root.join("words/de.txt")]);
+        assert_eq!(for_language(Some("fr")).unwrap().dictionaries, vec![data_home.join("spell_sweep/fr.txt")]);
+        assert!(for_language(Some("xx-unknown")).is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_unknown_key() {
+        let root: PathBuf = env::temp_dir().join("spell_sweep_config_unknown_key_test");
+        fs::create_dir_all(&root).unwrap();
+        let path: PathBuf = root.join(FILE_NAME);
+        fs::write(&path, "max_distance = 2\n").expect("Failed to write config");
+
+        assert!(Config::from_file(&path).is_err());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,391 @@
+use std::{
+    error::Error,
+    fs,
+    io::{BufRead, BufReader, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use serde_json::{json, Value};
+
+use crate::{
+    cache,
+    cmd::EXIT_CLEAN,
+    commands,
+    config::Config,
+    spell_check::{self, SpellCheck},
+};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// How often the daemon looks for changed dictionaries and word lists.
+const RELOAD_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A connection to a running daemon. Requests are newline-delimited JSON-RPC 2.0 messages, and
+/// every thread of a check shares the one connection.
+pub struct Client {
+    connection: Mutex<Connection>,
+}
+
+struct Connection {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+    next_id: u64,
+}
+
+impl Client {
+    /// Connects to the daemon listening on `socket`, if there is one.
+    pub fn connect(socket: &Path) -> Option<Self> {
+        let writer: UnixStream = UnixStream::connect(socket).ok()?;
+        let reader: BufReader<UnixStream> = BufReader::new(writer.try_clone().ok()?);
+
+        Some(Self {
+            connection: Mutex::new(Connection {
+                reader,
+                writer,
+                next_id: 0,
+            }),
+        })
+    }
+
+    fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let mut connection = self.connection.lock().map_err(|_| "The daemon connection was poisoned")?;
+        connection.next_id += 1;
+        let request: Value = json!({ "jsonrpc": "2.0", "id": connection.next_id, "method": method, "params": params });
+        writeln!(connection.writer, "{}", request)?;
+
+        let mut line: String = String::new();
+        if connection.reader.read_line(&mut line)? == 0 {
+            return Err("The daemon closed the connection".into());
+        }
+        let mut response: Value = serde_json::from_str(&line)?;
+        match response.get("error") {
+            Some(error) => Err(error["message"].as_str().unwrap_or("The daemon failed").to_string().into()),
+            None => Ok(response["result"].take()),
+        }
+    }
+
+    pub fn is_correct(&self, word: &str) -> Result<bool> {
+        Ok(self.check(&[word.to_string()])?[0])
+    }
+
+    /// Whether each of `words` is in the dictionary, asked in one request.
+    pub fn check(&self, words: &[String]) -> Result<Vec<bool>> {
+        let result: Value = self.call("check", json!({ "words": words }))?;
+        let answers: Vec<bool> = serde_json::from_value(result)?;
+        if answers.len() != words.len() {
+            return Err("The daemon did not answer for every word".into());
+        }
+
+        Ok(answers)
+    }
+
+    pub fn suggest(&self, word: &str, max_edit_distance: Option<u8>, suggestion_count: Option<usize>) -> Result<Vec<(String, u8)>> {
+        let result: Value = self.call(
+            "suggest",
+            json!({ "word": word, "max_edit_distance": max_edit_distance, "suggestions": suggestion_count }),
+        )?;
+
+        Ok(result
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|suggestion| Some((suggestion["word"].as_str()?.to_string(), suggestion["distance"].as_u64()? as u8)))
+            .collect())
+    }
+
+    /// Adds `words` to the word list at `list`, or to the personal word list, and returns the
+    /// words that were not in it yet.
+    pub fn add(&self, words: &[String], list: Option<&Path>) -> Result<Vec<String>> {
+        let result: Value = self.call("add", json!({ "words": words, "list": list }))?;
+        Ok(serde_json::from_value(result["added"].clone())?)
+    }
+}
+
+/// The loaded dictionary, along with the modification times of the files it was loaded from.
+struct State {
+    spell_check: SpellCheck,
+    stamps: Vec<(PathBuf, Option<SystemTime>)>,
+    /// The word lists loaded on top of the dictionary, so words added to them are accepted right away.
+    words: Vec<PathBuf>,
+}
+
+impl State {
+    /// Whether the words of the list at `list` are part of the loaded dictionary.
+    fn serves(&self, list: &Path) -> bool {
+        let canonical = |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        self.words.iter().any(|words| canonical(words) == canonical(list))
+    }
+}
+
+/// The files the dictionary is loaded from. The personal word list is watched even before it
+/// exists, so that creating it takes effect.
+fn watched_paths(config: &Config) -> Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = cache::dictionary_files(&cache::find_dictionaries(config)?);
+    paths.extend(config.words.iter().cloned());
+    paths.extend(cache::personal_words().filter(|path| !paths.contains(path)));
+
+    Ok(paths)
+}
+
+fn stamps(paths: &[PathBuf]) -> Vec<(PathBuf, Option<SystemTime>)> {
+    paths
+        .iter()
+        .map(|path| (path.clone(), fs::metadata(path).and_then(|metadata| metadata.modified()).ok()))
+        .collect()
+}
+
+fn load(config: &Config) -> Result<State> {
+    let mut config: Config = config.clone();
+    if let Some(personal_words) = cache::personal_words().filter(|path| path.is_file() && !config.words.contains(path)) {
+        config.words.push(personal_words);
+    }
+
+    // Clients apply their own locale and suggestion limits to what the daemon finds, since they
+    // are not part of the socket name the way the dictionaries are.
+    Ok(State {
+        spell_check: commands::load_local_spell_check(&config)?
+            .with_limits(None, None)
+            .with_locale(None),
+        stamps: stamps(&watched_paths(&config)?),
+        words: config.words.iter().cloned().chain(cache::personal_words()).collect(),
+    })
+}
+
+/// Keeps the dictionary loaded and answers requests on its socket until the process is stopped.
+/// Changed dictionaries and word lists are loaded again in the background, while requests keep
+/// being answered with the old ones.
+pub fn serve(config: &Config) -> Result<i32> {
+    let socket: PathBuf = cache::socket_path(config, &cache::find_dictionaries(config)?)
+        .ok_or("Could not find a directory for the socket; set XDG_RUNTIME_DIR or use --socket")?;
+    if UnixStream::connect(&socket).is_ok() {
+        return Err(format!("A daemon is already listening on {}", socket.display()).into());
+    }
+    // Whatever is left is the socket of a daemon that did not shut down cleanly.
+    let _ = fs::remove_file(&socket);
+    if let Some(parent) = socket.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let state: Arc<RwLock<State>> = Arc::new(RwLock::new(load(config)?));
+    let listener: UnixListener = UnixListener::bind(&socket).map_err(|err| format!("{}: {}", socket.display(), err))?;
+    eprintln!("Listening on {}", socket.display());
+
+    let watcher_state: Arc<RwLock<State>> = Arc::clone(&state);
+    let watcher_config: Config = config.clone();
+    thread::spawn(move || loop {
+        thread::sleep(RELOAD_INTERVAL);
+        let stale: bool = {
+            let state = watcher_state.read().expect("The daemon state was poisoned");
+            let paths: Vec<PathBuf> = state.stamps.iter().map(|(path, _)| path.clone()).collect();
+            stamps(&paths) != state.stamps
+        };
+        if stale {
+            match load(&watcher_config) {
+                Ok(reloaded) => {
+                    *watcher_state.write().expect("The daemon state was poisoned") = reloaded;
+                    eprintln!("Reloaded the dictionary");
+                }
+                Err(err) => {
+                    eprintln!("Error: {}; keeping the dictionary loaded before", err);
+                    // Not trying again until the files change once more keeps the error from
+                    // being repeated every interval.
+                    let mut state = watcher_state.write().expect("The daemon state was poisoned");
+                    let paths: Vec<PathBuf> = state.stamps.iter().map(|(path, _)| path.clone()).collect();
+                    state.stamps = stamps(&paths);
+                }
+            }
+        }
+    });
+
+    for stream in listener.incoming() {
+        let stream: UnixStream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                continue;
+            }
+        };
+        let state: Arc<RwLock<State>> = Arc::clone(&state);
+        thread::spawn(move || {
+            if let Err(err) = handle_connection(stream, &state) {
+                eprintln!("Error: {}", err);
+            }
+        });
+    }
+
+    Ok(EXIT_CLEAN)
+}
+
+fn handle_connection(stream: UnixStream, state: &RwLock<State>) -> Result<()> {
+    let mut writer: UnixStream = stream.try_clone()?;
+
+    for line in BufReader::new(stream).lines() {
+        let line: String = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response: Value = match serde_json::from_str::<Value>(&line) {
+            Ok(request) => match handle_request(&request, state) {
+                Ok(result) => json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }),
+                Err((code, message)) => {
+                    json!({ "jsonrpc": "2.0", "id": request["id"], "error": { "code": code, "message": message } })
+                }
+            },
+            Err(err) => json!({ "jsonrpc": "2.0", "id": null, "error": { "code": -32700, "message": err.to_string() } }),
+        };
+        writeln!(writer, "{}", response)?;
+    }
+
+    Ok(())
+}
+
+/// Answers one request, or returns a JSON-RPC error code and message.
+fn handle_request(request: &Value, state: &RwLock<State>) -> std::result::Result<Value, (i32, String)> {
+    let params: &Value = &request["params"];
+    let invalid = |message: &str| (-32602, message.to_string());
+
+    match request["method"].as_str() {
+        Some("check") => {
+            let words: &Vec<Value> = params["words"].as_array().ok_or_else(|| invalid("check needs a list of words"))?;
+            let state = state.read().expect("The daemon state was poisoned");
+            Ok(words
+                .iter()
+                .map(|word| Value::Bool(word.as_str().is_some_and(|word| state.spell_check.is_correct(word))))
+                .collect())
+        }
+        Some("suggest") => {
+            let word: &str = params["word"].as_str().ok_or_else(|| invalid("suggest needs a word"))?;
+            let max_edit_distance: Option<u8> = params["max_edit_distance"].as_u64().map(|distance| distance.min(u8::MAX as u64) as u8);
+            let count: Option<usize> = params["suggestions"].as_u64().map(|count| count as usize);
+            let state = state.read().expect("The daemon state was poisoned");
+
+            // The nearest suggestions are all found at the same distance, so applying the limits
+            // afterwards gives what a limited search would.
+            Ok(spell_check::limit_suggestions(state.spell_check.suggest(word), max_edit_distance, count)
+                .into_iter()
+                .map(|(suggestion, distance)| json!({ "word": suggestion, "distance": distance }))
+                .collect())
+        }
+        Some("add") => {
+            let words: Vec<String> = serde_json::from_value(params["words"].clone()).map_err(|err| invalid(&err.to_string()))?;
+            let list: PathBuf = match params["list"].as_str() {
+                Some(list) => PathBuf::from(list),
+                None => cache::personal_words().ok_or_else(|| (-32603, "There is no personal word list".to_string()))?,
+            };
+            let added: Vec<String> = commands::append_words(&words, &list)
+                .map_err(|err| (-32603, err.to_string()))?
+                .into_iter()
+                .map(str::to_string)
+                .collect();
+
+            let mut state = state.write().expect("The daemon state was poisoned");
+            // Words added to a list the dictionary was not loaded with stay out of it, as they
+            // would be after a restart.
+            if !state.serves(&list) {
+                return Ok(json!({ "added": added }));
+            }
+            state.spell_check.add_words(added.iter().cloned());
+            // The change is already applied, so it does not need a reload.
+            let paths: Vec<PathBuf> = state.stamps.iter().map(|(path, _)| path.clone()).collect();
+            state.stamps = stamps(&paths);
+            Ok(json!({ "added": added }))
+        }
+        Some(method) => Err((-32601, format!("Unknown method {}", method))),
+        None => Err((-32600, "The request has no method".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env, fs,
+        os::unix::net::UnixListener,
+        path::PathBuf,
+        sync::{Arc, RwLock},
+        thread,
+    };
+
+    use serde_json::{json, Value};
+
+    use super::{handle_connection, handle_request, Client, State};
+    use crate::{dictionary::Dictionary, processor, spell_check::SpellCheck};
+
+    #[test]
+    fn test_handle_request() {
+        let root: PathBuf = env::temp_dir().join("spell_sweep_daemon_test");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).expect("Failed to create directories");
+        let words: Vec<String> = ["hello", "help", "hell", "world"].iter().map(|word| word.to_string()).collect();
+        let state: RwLock<State> = RwLock::new(State {
+            spell_check: SpellCheck::from(&Dictionary::from((words, 255))),
+            stamps: Vec::new(),
+            words: vec![root.join("words.txt")],
+        });
+        let request = |method: &str, params: Value| json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+
+        assert_eq!(
+            handle_request(&request("check", json!({ "words": ["hello", "wrold"] })), &state),
+            Ok(json!([true, false]))
+        );
+        assert_eq!(
+            handle_request(&request("suggest", json!({ "word": "helo", "suggestions": 2 })), &state),
+            Ok(json!([{ "word": "hell", "distance": 1 }, { "word": "help", "distance": 1 }]))
+        );
+        assert_eq!(
+            handle_request(&request("suggest", json!({ "word": "hxxxo", "max_edit_distance": 1 })), &state),
+            Ok(json!([]))
+        );
+        assert_eq!(handle_request(&request("check", json!({})), &state).unwrap_err().0, -32602);
+        assert_eq!(handle_request(&request("stop", json!({})), &state).unwrap_err().0, -32601);
+
+        let other: PathBuf = root.join("other.txt");
+        assert_eq!(
+            handle_request(&request("add", json!({ "words": ["rkyv"], "list": other })), &state),
+            Ok(json!({ "added": ["rkyv"] }))
+        );
+        assert_eq!(fs::read_to_string(&other).unwrap(), "rkyv\n");
+        assert_eq!(handle_request(&request("check", json!({ "words": ["rkyv"] })), &state), Ok(json!([false])));
+        handle_request(&request("add", json!({ "words": ["rkyv"], "list": root.join("words.txt") })), &state)
+            .expect("Failed to add the word");
+        assert_eq!(handle_request(&request("check", json!({ "words": ["rkyv"] })), &state), Ok(json!([true])));
+
+        fs::remove_dir_all(&root).expect("Failed to remove test directory");
+    }
+
+    #[test]
+    fn test_client() {
+        let words: Vec<String> = ["hello", "help", "hell", "world", "child", "state", "art"].iter().map(|word| word.to_string()).collect();
+        let state: Arc<RwLock<State>> = Arc::new(RwLock::new(State {
+            spell_check: SpellCheck::from(&Dictionary::from((words, 255))),
+            stamps: Vec::new(),
+            words: Vec::new(),
+        }));
+        let socket: PathBuf = env::temp_dir().join(format!("spell_sweep_client_test_{}.sock", std::process::id()));
+        let _ = fs::remove_file(&socket);
+        let listener: UnixListener = UnixListener::bind(&socket).expect("Failed to bind the socket");
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().expect("Failed to accept a connection");
+            handle_connection(stream, &state).expect("Failed to answer the client");
+        });
+
+        let client: Client = Client::connect(&socket).expect("Failed to connect to the daemon");
+        fs::remove_file(&socket).expect("Failed to remove the socket");
+        assert_eq!(client.check(&["hello".to_string(), "wrold".to_string()]).unwrap(), vec![true, false]);
+
+        let spell_check: SpellCheck = SpellCheck::with_daemon(client, || Err("The daemon should answer".into()))
+            .with_words(["extra".to_string()])
+            .with_limits(None, Some(1));
+        let text: &str = "hello child's extra state-of-the-art wrold";
+        let misspelled: Vec<String> = spell_check
+            .misspelled(processor::tokenize(text, processor::NON_WORDS))
+            .into_iter()
+            .map(|token| token.word.clone())
+            .collect();
+        assert_eq!(misspelled, vec!["of", "the", "wrold"]);
+        assert_eq!(spell_check.suggest("helo"), vec![("hell".to_string(), 1)]);
+    }
+}
@@ -1,22 +1,36 @@
-use std::{fs::File, io::{BufRead, BufReader}, rc::Rc};
+use std::{collections::HashSet, fs::File, io::{BufRead, BufReader}, sync::Arc};
+
+use crate::processor;
 
 #[derive(Debug)]
 #[readonly::make]
 pub struct Dictionary {
-    pub words: Vec<Rc<String>>,
+    pub words: Vec<Arc<String>>,
     pub max_word_length: u16,
     pub alphabet_length: u16,
+    /// Words that are accepted but never suggested.
+    pub no_suggest: HashSet<String>,
+    /// Replacements that turn a common misspelling into its correction, tried before the
+    /// suggestions found by edit distance.
+    pub replacements: Vec<(String, String)>,
 }
 
 impl From<(File, u16)> for Dictionary {
     fn from(value: (File, u16)) -> Self {
         let buf_reader: BufReader<File> = BufReader::new(value.0);
+        let lines: Vec<String> = buf_reader.lines().map_while(Result::ok).collect();
+
+        Self::from((lines, value.1))
+    }
+}
 
-        let mut words: Vec<Rc<String>> = Vec::new();
+impl From<(Vec<String>, u16)> for Dictionary {
+    fn from(value: (Vec<String>, u16)) -> Self {
+        let mut words: Vec<Arc<String>> = Vec::new();
         let mut max_word_length: u16 = 0;
 
-        for line in buf_reader.lines().filter_map(Result::ok) {
-            let word: Rc<String> = Rc::new(line.to_lowercase());
+        for line in value.0 {
+            let word: Arc<String> = Arc::new(processor::normalize_apostrophes(&line.to_lowercase()));
             max_word_length = std::cmp::max(word.len() as u16, max_word_length);
             words.push(word);
         }
@@ -24,11 +38,27 @@ impl From<(File, u16)> for Dictionary {
         Self {
             words,
             max_word_length,
-            alphabet_length: value.1
+            alphabet_length: value.1,
+            no_suggest: HashSet::new(),
+            replacements: Vec::new(),
         }
     }
 }
 
+impl Dictionary {
+    /// Keeps `no_suggest` from being suggested and tries `replacements` on misspelled words.
+    pub fn with_rules(mut self, no_suggest: Vec<String>, replacements: Vec<(String, String)>) -> Self {
+        self.no_suggest
+            .extend(no_suggest.iter().map(|word| processor::normalize_apostrophes(&word.to_lowercase())));
+        self.replacements.extend(
+            replacements
+                .into_iter()
+                .map(|(from, to)| (from.to_lowercase(), to.to_lowercase())),
+        );
+        self
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::fs::File;
@@ -46,4 +76,14 @@ mod tests {
 
         assert_eq!(dictionary.max_word_length, max_word_length);
     }
+
+    #[test]
+    fn test_from_words() {
+        let words: Vec<String> = vec!["Hello".to_string(), "world".to_string()];
+        let dictionary: Dictionary = Dictionary::from((words, 255));
+
+        assert_eq!(dictionary.words.len(), 2);
+        assert_eq!(dictionary.words[0].as_str(), "hello");
+        assert_eq!(dictionary.max_word_length, 5);
+    }
 }
@@ -0,0 +1,112 @@
+use std::collections::HashSet;
+
+use crate::processor::{self, Token};
+
+const PREFIX: &str = "spell-sweep:";
+
+/// The state of the inline directives read so far. Directives are recognised anywhere in the
+/// text, including comments that are otherwise not checked:
+///
+/// - `spell-sweep:ignore-next-line` skips the line after it.
+/// - `spell-sweep:disable` and `spell-sweep:enable` skip every line between them.
+/// - `spell-sweep:words foo bar` accepts the listed words from there to the end of the file.
+///
+/// Lines holding a directive are never checked themselves.
+#[derive(Debug, Default)]
+pub struct Directives {
+    disabled: bool,
+    ignore_next_line: bool,
+    words: HashSet<String>,
+}
+
+impl Directives {
+    /// Reads the directives on `line` and returns whether the line is checked.
+    fn read_line(&mut self, line: &str) -> bool {
+        let mut checked: bool = !self.disabled && !self.ignore_next_line;
+        self.ignore_next_line = false;
+
+        for (idx, _) in line.match_indices(PREFIX) {
+            let rest: &str = line[idx + PREFIX.len()..].trim_start();
+            let directive: &str = rest.split(|c: char| c.is_whitespace()).next().unwrap_or_default();
+
+            match directive {
+                "ignore-next-line" => self.ignore_next_line = true,
+                "disable" => self.disabled = true,
+                "enable" => self.disabled = false,
+                "words" => self.words.extend(
+                    rest["words".len()..]
+                        .split_whitespace()
+                        .take_while(|word| !word.starts_with("-->") && !word.starts_with("*/"))
+                        .map(|word| processor::normalize_apostrophes(&word.to_lowercase())),
+                ),
+                _ => continue,
+            }
+            checked = false;
+        }
+
+        checked
+    }
+
+    /// Drops the tokens of `text` that directives exclude, reading the directives on every line of
+    /// `text` along the way. `tokens` must be sorted by position. Calling this line by line gives
+    /// the same result as calling it once on the whole text.
+    pub fn filter(&mut self, text: &str, tokens: Vec<Token>) -> Vec<Token> {
+        let mut tokens = tokens.into_iter().peekable();
+        let mut kept: Vec<Token> = Vec::new();
+        let mut line_end: usize = 0;
+
+        for line in text.split_inclusive('\n') {
+            line_end += line.len();
+            let checked: bool = self.read_line(line);
+
+            while let Some(token) = tokens.next_if(|token| token.start < line_end) {
+                if checked && !self.words.contains(&processor::normalize_apostrophes(&token.word.to_lowercase())) {
+                    kept.push(token);
+                }
+            }
+        }
+
+        kept
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Directives;
+    use crate::processor::{self, Token};
+
+    fn checked(text: &str) -> Vec<String> {
+        let tokens: Vec<Token> = processor::tokenize(text, processor::NON_WORDS);
+        Directives::default().filter(text, tokens).into_iter().map(|token| token.word.clone()).collect()
+    }
+
+    #[test]
+    fn test_directives() {
+        let text: &str = "one\n\
+                          # spell-sweep:ignore-next-line\n\
+                          two\n\
+                          three\n\
+                          <!-- spell-sweep:disable -->\n\
+                          four\n\
+                          <!-- spell-sweep: enable -->\n\
+                          five Foo\n\
+                          /* spell-sweep:words foo bar */ six\n\
+                          seven foo BAR baz";
+
+        assert_eq!(checked(text), vec!["one", "three", "five", "Foo", "seven", "baz"]);
+    }
+
+    #[test]
+    fn test_line_by_line() {
+        let text: &str = "spell-sweep:disable\nzero\nspell-sweep:enable\nspell-sweep:words qux\nqux one\n";
+        let mut directives: Directives = Directives::default();
+        let mut words: Vec<String> = Vec::new();
+
+        for line in text.split_inclusive('\n') {
+            let tokens: Vec<Token> = processor::tokenize(line, processor::NON_WORDS);
+            words.extend(directives.filter(line, tokens).into_iter().map(|token| token.word.clone()));
+        }
+
+        assert_eq!(words, vec!["one"]);
+    }
+}
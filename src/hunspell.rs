@@ -0,0 +1,386 @@
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// The words of a hunspell dictionary with its affix rules applied, along with what the affix
+/// file says about suggesting them.
+#[derive(Debug, Default, PartialEq)]
+pub struct Expansion {
+    /// Every accepted word, the ones in `no_suggest` included.
+    pub words: Vec<String>,
+    /// Words that are accepted but never suggested.
+    pub no_suggest: Vec<String>,
+    /// Replacements that turn a common misspelling into its correction, from the REP table.
+    pub replacements: Vec<(String, String)>,
+}
+
+/// The `.aff` file next to a `.dic` file, if `dictionary` is one and the file exists.
+pub fn affix_file(dictionary: &Path) -> Option<PathBuf> {
+    if dictionary.extension().is_none_or(|extension| extension != "dic") {
+        return None;
+    }
+
+    Some(dictionary.with_extension("aff")).filter(|path| path.is_file())
+}
+
+/// Reads the hunspell dictionary `dic` with the affix file `aff` and expands every word into all
+/// of its forms.
+pub fn load(dic: &Path, aff: &Path) -> Result<Expansion> {
+    let aff_bytes: Vec<u8> = fs::read(aff).map_err(|err| format!("{}: {}", aff.display(), err))?;
+    let dic_bytes: Vec<u8> = fs::read(dic).map_err(|err| format!("{}: {}", dic.display(), err))?;
+    let encoding: Encoding = Encoding::of(&aff_bytes).map_err(|err| format!("{}: {}", aff.display(), err))?;
+
+    let affixes: Affixes = Affixes::parse(&encoding.decode(&aff_bytes)).map_err(|err| format!("{}: {}", aff.display(), err))?;
+    Ok(affixes.expand(&encoding.decode(&dic_bytes)))
+}
+
+/// The character sets an affix file can declare with `SET`. Both files of a dictionary use it.
+enum Encoding {
+    Utf8,
+    Latin1,
+}
+
+impl Encoding {
+    fn of(aff_bytes: &[u8]) -> Result<Self> {
+        // `SET` is plain ASCII, so it can be found before knowing how to decode the rest.
+        let set: Option<String> = aff_bytes
+            .split(|byte| *byte == b'\n')
+            .map(|line| String::from_utf8_lossy(line).to_string())
+            .find_map(|line| line.strip_prefix("SET").map(|encoding| encoding.trim().to_uppercase()));
+
+        match set.as_deref() {
+            None | Some("UTF-8") => Ok(Self::Utf8),
+            Some("ISO8859-1") | Some("ISO-8859-1") => Ok(Self::Latin1),
+            Some(encoding) => Err(format!("Unsupported encoding {}; convert the dictionary to UTF-8", encoding).into()),
+        }
+    }
+
+    fn decode(&self, bytes: &[u8]) -> String {
+        match self {
+            Self::Utf8 => String::from_utf8_lossy(bytes).trim_start_matches('\u{feff}').to_string(),
+            Self::Latin1 => bytes.iter().map(|byte| *byte as char).collect(),
+        }
+    }
+}
+
+/// How the flags of words and affixes are written, set by `FLAG`.
+#[derive(Clone, Copy)]
+enum FlagStyle {
+    /// One character per flag, the default.
+    Char,
+    /// Two characters per flag.
+    Long,
+    /// Comma-separated numbers.
+    Num,
+}
+
+impl FlagStyle {
+    fn split(self, flags: &str) -> Vec<String> {
+        match self {
+            Self::Char => flags.chars().map(String::from).collect(),
+            Self::Long => flags
+                .chars()
+                .collect::<Vec<char>>()
+                .chunks(2)
+                .map(|pair| pair.iter().collect())
+                .collect(),
+            Self::Num => flags.split(',').map(|flag| flag.trim().to_string()).filter(|flag| !flag.is_empty()).collect(),
+        }
+    }
+}
+
+/// One element of an affix condition.
+#[derive(Debug, PartialEq)]
+enum Pattern {
+    Any,
+    Char(char),
+    Set { chars: Vec<char>, negated: bool },
+}
+
+impl Pattern {
+    fn parse(condition: &str) -> Vec<Self> {
+        if condition == "." {
+            return Vec::new();
+        }
+
+        let mut patterns: Vec<Self> = Vec::new();
+        let mut chars = condition.chars();
+        while let Some(c) = chars.next() {
+            patterns.push(match c {
+                '.' => Self::Any,
+                '[' => {
+                    let mut set: Vec<char> = chars.by_ref().take_while(|c| *c != ']').collect();
+                    let negated: bool = set.first() == Some(&'^');
+                    if negated {
+                        set.remove(0);
+                    }
+                    Self::Set { chars: set, negated }
+                }
+                c => Self::Char(c),
+            });
+        }
+
+        patterns
+    }
+
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Char(expected) => c == *expected,
+            Self::Set { chars, negated } => chars.contains(&c) != *negated,
+        }
+    }
+}
+
+/// A `PFX` or `SFX` rule: `strip` is removed from the word and `affix` put in its place, if the
+/// word matches `condition` at that end.
+#[derive(Debug)]
+struct Rule {
+    strip: String,
+    affix: String,
+    condition: Vec<Pattern>,
+}
+
+impl Rule {
+    fn apply_suffix(&self, word: &str) -> Option<String> {
+        let chars: Vec<char> = word.chars().collect();
+        let stem: &str = word.strip_suffix(self.strip.as_str())?;
+        if stem.is_empty() || chars.len() < self.condition.len() {
+            return None;
+        }
+        let tail: &[char] = &chars[chars.len() - self.condition.len()..];
+        if !self.condition.iter().zip(tail).all(|(pattern, c)| pattern.matches(*c)) {
+            return None;
+        }
+
+        Some(format!("{}{}", stem, self.affix))
+    }
+
+    fn apply_prefix(&self, word: &str) -> Option<String> {
+        let chars: Vec<char> = word.chars().collect();
+        let stem: &str = word.strip_prefix(self.strip.as_str())?;
+        if stem.is_empty() || chars.len() < self.condition.len() {
+            return None;
+        }
+        if !self.condition.iter().zip(&chars).all(|(pattern, c)| pattern.matches(*c)) {
+            return None;
+        }
+
+        Some(format!("{}{}", self.affix, stem))
+    }
+}
+
+/// The rules of one affix flag.
+#[derive(Debug)]
+struct AffixClass {
+    /// Whether the rules combine with the rules of the other kind, as in `un-do-able`.
+    cross_product: bool,
+    rules: Vec<Rule>,
+}
+
+/// The supported part of an affix file.
+struct Affixes {
+    flag_style: FlagStyle,
+    prefixes: HashMap<String, AffixClass>,
+    suffixes: HashMap<String, AffixClass>,
+    replacements: Vec<(String, String)>,
+    no_suggest: Option<String>,
+    forbidden: Option<String>,
+    need_affix: Option<String>,
+}
+
+impl Affixes {
+    fn parse(contents: &str) -> Result<Self> {
+        let mut affixes: Affixes = Affixes {
+            flag_style: FlagStyle::Char,
+            prefixes: HashMap::new(),
+            suffixes: HashMap::new(),
+            replacements: Vec::new(),
+            no_suggest: None,
+            forbidden: None,
+            need_affix: None,
+        };
+        // How many rules each PFX or SFX header has yet to be followed by. Until they are read, a
+        // line of the flag is a rule even if it looks like a header, as `SFX V 0 4` does.
+        let mut rules_left: HashMap<(&str, &str), usize> = HashMap::new();
+
+        for (line_number, line) in contents.lines().enumerate() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let invalid = || format!("line {}: invalid {} entry", line_number + 1, fields[0]);
+            let is_rule: bool = match fields.as_slice() {
+                [kind @ ("PFX" | "SFX"), flag, ..] => match rules_left.get_mut(&(*kind, *flag)) {
+                    Some(left) if *left > 0 => {
+                        *left -= 1;
+                        true
+                    }
+                    _ => false,
+                },
+                _ => false,
+            };
+            match fields.as_slice() {
+                ["FLAG", "long"] => affixes.flag_style = FlagStyle::Long,
+                ["FLAG", "num"] => affixes.flag_style = FlagStyle::Num,
+                ["FLAG", _] => affixes.flag_style = FlagStyle::Char,
+                ["NOSUGGEST", flag] => affixes.no_suggest = Some(flag.to_string()),
+                ["FORBIDDENWORD", flag] => affixes.forbidden = Some(flag.to_string()),
+                ["NEEDAFFIX", flag] => affixes.need_affix = Some(flag.to_string()),
+                // The first REP line only gives the number of entries.
+                ["REP", _] => {}
+                ["REP", from, to, ..] => affixes.replacements.push((from.to_string(), to.to_string())),
+                [kind @ ("PFX" | "SFX"), flag, cross_product, count] if !is_rule && count.parse::<usize>().is_ok() => {
+                    rules_left.insert((*kind, *flag), count.parse().unwrap_or(0));
+                    let class: AffixClass = AffixClass {
+                        cross_product: *cross_product == "Y",
+                        rules: Vec::new(),
+                    };
+                    match *kind {
+                        "PFX" => affixes.prefixes.insert(flag.to_string(), class),
+                        _ => affixes.suffixes.insert(flag.to_string(), class),
+                    };
+                }
+                [kind @ ("PFX" | "SFX"), flag, strip, affix, rest @ ..] => {
+                    let classes: &mut HashMap<String, AffixClass> = match *kind {
+                        "PFX" => &mut affixes.prefixes,
+                        _ => &mut affixes.suffixes,
+                    };
+                    let class: &mut AffixClass = classes.get_mut(*flag).ok_or_else(invalid)?;
+                    // Continuation flags after a `/` are not supported and are dropped.
+                    let affix: &str = affix.split('/').next().unwrap_or("");
+                    class.rules.push(Rule {
+                        strip: if *strip == "0" { String::new() } else { strip.to_string() },
+                        affix: if affix == "0" { String::new() } else { affix.to_string() },
+                        condition: Pattern::parse(rest.first().unwrap_or(&".")),
+                    });
+                }
+                [kind @ ("PFX" | "SFX"), ..] => return Err(format!("line {}: invalid {} entry", line_number + 1, kind).into()),
+                _ => {}
+            }
+        }
+
+        Ok(affixes)
+    }
+
+    /// Expands every entry of the `.dic` file. Forbidden words are left out, even when another
+    /// entry produces them.
+    fn expand(&self, dic: &str) -> Expansion {
+        let mut words: Vec<String> = Vec::new();
+        let mut no_suggest: Vec<String> = Vec::new();
+        let mut forbidden: HashSet<String> = HashSet::new();
+
+        for (line_number, line) in dic.lines().enumerate() {
+            let entry: &str = line.split(['\t', ' ']).next().unwrap_or("").trim();
+            // The first line holds the approximate number of entries.
+            if entry.is_empty() || (line_number == 0 && entry.parse::<usize>().is_ok()) {
+                continue;
+            }
+            let (word, flags): (&str, Vec<String>) = match entry.split_once('/') {
+                Some((word, flags)) => (word, self.flag_style.split(flags)),
+                None => (entry, Vec::new()),
+            };
+            let has = |flag: &Option<String>| flag.as_ref().is_some_and(|flag| flags.contains(flag));
+
+            let forms: Vec<String> = self.forms(word, &flags, has(&self.need_affix));
+            if has(&self.forbidden) {
+                forbidden.extend(forms);
+            } else if has(&self.no_suggest) {
+                no_suggest.extend(forms);
+            } else {
+                words.extend(forms);
+            }
+        }
+
+        let mut seen: HashSet<String> = HashSet::new();
+        words.retain(|word| !forbidden.contains(word) && seen.insert(word.clone()));
+        no_suggest.retain(|word| !forbidden.contains(word) && seen.insert(word.clone()));
+        words.extend(no_suggest.iter().cloned());
+
+        Expansion {
+            words,
+            no_suggest,
+            replacements: self.replacements.clone(),
+        }
+    }
+
+    /// `word` with every suffix and prefix of `flags` applied, and each prefix applied to the
+    /// suffixed forms it combines with.
+    fn forms(&self, word: &str, flags: &[String], need_affix: bool) -> Vec<String> {
+        let mut forms: Vec<String> = Vec::new();
+        if !need_affix {
+            forms.push(word.to_string());
+        }
+
+        let mut cross_suffixed: Vec<String> = Vec::new();
+        for class in flags.iter().filter_map(|flag| self.suffixes.get(flag)) {
+            for form in class.rules.iter().filter_map(|rule| rule.apply_suffix(word)) {
+                if class.cross_product {
+                    cross_suffixed.push(form.clone());
+                }
+                forms.push(form);
+            }
+        }
+        for class in flags.iter().filter_map(|flag| self.prefixes.get(flag)) {
+            for rule in class.rules.iter() {
+                forms.extend(rule.apply_prefix(word));
+                if class.cross_product {
+                    forms.extend(cross_suffixed.iter().filter_map(|form| rule.apply_prefix(form)));
+                }
+            }
+        }
+
+        forms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf};
+
+    use super::{affix_file, load, Expansion};
+
+    #[test]
+    fn test_load() {
+        let root: PathBuf = env::temp_dir().join("spell_sweep_hunspell_test");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).expect("Failed to create directory");
+        let dic: PathBuf = root.join("en_TEST.dic");
+        fs::write(
+            root.join("en_TEST.aff"),
+            "SET UTF-8\n\
+             NOSUGGEST !\n\
+             FORBIDDENWORD *\n\
+             REP 2\n\
+             REP f ph\n\
+             REP alot a_lot\n\
+             PFX U Y 1\n\
+             PFX U 0 un .\n\
+             SFX S Y 2\n\
+             SFX S y ies [^aeiou]y\n\
+             SFX S 0 s [^y]\n\
+             SFX D N 1\n\
+             SFX D 0 ed [^e]\n\
+             SFX V N 1\n\
+             SFX V 0 4\n",
+        )
+        .expect("Failed to write affix file");
+        fs::write(&dic, "6\nfly/S\ndo/U\nlock/USD\ndamn/!\nlocks/*\nipv/V\n").expect("Failed to write dictionary");
+
+        assert_eq!(affix_file(&dic), Some(root.join("en_TEST.aff")));
+        assert_eq!(affix_file(&root.join("en_TEST.txt")), None);
+
+        let mut expansion: Expansion = load(&dic, &root.join("en_TEST.aff")).unwrap();
+        expansion.words.sort();
+        assert_eq!(expansion.words, vec!["damn", "do", "flies", "fly", "ipv", "ipv4", "lock", "locked", "undo", "unlock", "unlocks"]);
+        assert_eq!(expansion.no_suggest, vec!["damn"]);
+        assert_eq!(
+            expansion.replacements,
+            vec![("f".to_string(), "ph".to_string()), ("alot".to_string(), "a_lot".to_string())]
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,207 @@
+use std::{
+    error::Error,
+    io::{self, BufRead, Write},
+    path::PathBuf,
+};
+
+use crate::{
+    cache,
+    cmd::{CmdData, EXIT_CLEAN},
+    commands,
+    directives::Directives,
+    pipeline,
+    processor::Token,
+    spell_check::{self, SpellCheck},
+    syntax::Syntax,
+};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// The version the protocol is spoken at, which is what clients check the banner for.
+const ISPELL_VERSION: &str = "3.1.20";
+
+/// A session of the `ispell -a` pipe protocol. Every line of input is either a command, marked by
+/// its first character, or text to check, which is answered with one result line per word and an
+/// empty line.
+struct Session<'a> {
+    spell_check: SpellCheck,
+    cmd_data: &'a CmdData,
+    directives: Directives,
+    /// Leaves out the lines of correct words, set by `!` and cleared by `%`.
+    terse: bool,
+    /// Reads the text as LaTeX, set by `+` and cleared by `-`.
+    tex: bool,
+    /// Words added with `*` or `&` that are written to the personal word list by `#`.
+    personal_words: Vec<String>,
+    /// Where `#` writes the personal words.
+    personal_list: Option<PathBuf>,
+}
+
+/// Speaks the `ispell -a` protocol on stdin and stdout until stdin is closed, so editors that
+/// drive ispell or aspell can use spell_sweep instead.
+pub fn serve(spell_check: SpellCheck, cmd_data: &CmdData) -> Result<i32> {
+    let mut session: Session = Session::new(spell_check, cmd_data, cache::personal_words());
+    session.run(io::stdin().lock(), &mut io::stdout().lock())?;
+
+    Ok(EXIT_CLEAN)
+}
+
+impl<'a> Session<'a> {
+    fn new(spell_check: SpellCheck, cmd_data: &'a CmdData, personal_list: Option<PathBuf>) -> Self {
+        Self {
+            spell_check,
+            cmd_data,
+            directives: Directives::default(),
+            terse: false,
+            tex: false,
+            personal_words: Vec::new(),
+            personal_list,
+        }
+    }
+
+    fn run<R: BufRead, W: Write>(&mut self, reader: R, writer: &mut W) -> Result<()> {
+        writeln!(
+            writer,
+            "@(#) International Ispell Version {} (but really {} {})",
+            ISPELL_VERSION,
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION")
+        )?;
+        writer.flush()?;
+
+        for line in reader.lines() {
+            let line: String = line?;
+            let line: &str = line.strip_suffix('\r').unwrap_or(&line);
+            match line.chars().next() {
+                Some('*') => self.add_personal_word(&line[1..]),
+                Some('&') => self.add_personal_word(&line[1..].to_lowercase()),
+                Some('@') => self.spell_check.add_words([line[1..].trim().to_string()]),
+                Some('#') => self.save_personal_words(),
+                Some('!') => self.terse = true,
+                Some('%') => self.terse = false,
+                Some('+') => self.tex = true,
+                Some('-') => self.tex = false,
+                // File name hints and aspell's `$$` extensions are accepted and ignored.
+                Some('~' | '$') => {}
+                Some('^') => self.check_line(writer, &line[1..], 1)?,
+                _ => self.check_line(writer, line, 0)?,
+            }
+            writer.flush()?;
+        }
+
+        Ok(())
+    }
+
+    fn add_personal_word(&mut self, word: &str) {
+        let word: &str = word.trim();
+        if !word.is_empty() {
+            self.spell_check.add_words([word.to_string()]);
+            self.personal_words.push(word.to_string());
+        }
+    }
+
+    fn save_personal_words(&mut self) {
+        let Some(list) = &self.personal_list else {
+            eprintln!("Error: Could not find a data directory for the personal word list; set XDG_DATA_HOME");
+            return;
+        };
+        match commands::append_words(&self.personal_words, list) {
+            Ok(_) => self.personal_words.clear(),
+            Err(err) => eprintln!("Error: {}", err),
+        }
+    }
+
+    /// Answers a line of text. Offsets count characters of the line as it was received, so the
+    /// `^` that escapes a line is counted by `offset`.
+    fn check_line<W: Write>(&mut self, writer: &mut W, text: &str, offset: usize) -> Result<()> {
+        let syntax: Syntax = match self.tex {
+            true => Syntax::Latex,
+            false => self.cmd_data.syntax.unwrap_or(Syntax::Text),
+        };
+        let tokens: Vec<Token> = pipeline::tokenize(self.cmd_data, syntax, text, &mut self.directives);
+
+        for token in tokens {
+            if self.spell_check.is_correct(&token.word) {
+                if !self.terse {
+                    match self.spell_check.root(&token.word) {
+                        Some(root) => writeln!(writer, "+ {}", root)?,
+                        None => writeln!(writer, "*")?,
+                    }
+                }
+                continue;
+            }
+
+            let misspelled: Vec<Token> = self.spell_check.misspelled(vec![token]);
+            if misspelled.is_empty() {
+                // A compound whose every component is correct.
+                if !self.terse {
+                    writeln!(writer, "-")?;
+                }
+                continue;
+            }
+            for token in misspelled {
+                let column: usize = offset + text[..token.start].chars().count();
+                let suggestions: Vec<String> = self
+                    .spell_check
+                    .suggest(&token.word)
+                    .into_iter()
+                    .map(|(suggestion, _)| spell_check::convert_case(&suggestion, &token.word))
+                    .collect();
+                match suggestions.is_empty() {
+                    true => writeln!(writer, "# {} {}", token.word, column)?,
+                    false => writeln!(
+                        writer,
+                        "& {} {} {}: {}",
+                        token.word,
+                        suggestions.len(),
+                        column,
+                        suggestions.join(", ")
+                    )?,
+                }
+            }
+        }
+        writeln!(writer)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf};
+
+    use super::Session;
+    use crate::{cmd::CmdData, dictionary::Dictionary, spell_check::SpellCheck};
+
+    #[test]
+    fn test_session() {
+        let root: PathBuf = env::temp_dir().join("spell_sweep_ispell_test");
+        let _ = fs::remove_dir_all(&root);
+        let list: PathBuf = root.join("words.txt");
+
+        let words: Vec<String> = ["hello", "help", "world", "state", "of", "the", "art", "child"]
+            .iter()
+            .map(|word| word.to_string())
+            .collect();
+        let cmd_data: CmdData = CmdData::default();
+        let mut session: Session = Session::new(
+            SpellCheck::from(&Dictionary::from((words, 255))).with_limits(Some(2), None),
+            &cmd_data,
+            Some(list.clone()),
+        );
+        let input: &str = "Hello wrold\n^child's state-of-the-art xqzzv\n!\nhello helo\n@helo\n*Rkyv\n#\nhelo rkyv\n";
+        let mut output: Vec<u8> = Vec::new();
+        session.run(input.as_bytes(), &mut output).unwrap();
+
+        let output: String = String::from_utf8(output).unwrap();
+        let mut lines = output.lines();
+        assert!(lines.next().unwrap().starts_with("@(#) International Ispell Version 3.1.20"));
+        assert_eq!(
+            lines.collect::<Vec<&str>>(),
+            vec!["*", "& wrold 1 6: world", "", "+ child", "-", "# xqzzv 26", "", "& helo 2 6: help, hello", "", ""]
+        );
+        assert_eq!(fs::read_to_string(&list).unwrap(), "Rkyv\n");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
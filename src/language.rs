@@ -0,0 +1,159 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    ops::Range,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    commands,
+    config::{self, Config},
+    processor::Token,
+    spell_check::SpellCheck,
+    syntax::{html, markdown, Syntax},
+};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// The languages a document declares, for the whole of it and for sections of it.
+#[derive(Debug, Default, PartialEq)]
+pub struct Declared {
+    pub document: Option<String>,
+    /// Byte ranges with their language, where nested sections come after the ones around them.
+    pub sections: Vec<(Range<usize>, String)>,
+}
+
+impl Declared {
+    /// Reads the `lang` of Markdown front matter and the `lang` attributes of HTML, in HTML and
+    /// XML documents and in the inline HTML of Markdown.
+    pub fn find(syntax: Syntax, text: &str) -> Self {
+        match syntax {
+            Syntax::Markdown => Self {
+                document: markdown::front_matter_language(text),
+                sections: html::language_sections(text),
+            },
+            Syntax::Html => Self {
+                document: None,
+                sections: html::language_sections(text),
+            },
+            _ => Self::default(),
+        }
+    }
+
+    /// The language of the text at `offset`: that of the innermost section around it, or else
+    /// that of the document.
+    pub fn language_at(&self, offset: usize) -> Option<&str> {
+        self.sections
+            .iter()
+            .rev()
+            .find(|(range, _)| range.contains(&offset))
+            .map(|(_, lang)| lang.as_str())
+            .or(self.document.as_deref())
+    }
+
+    /// Splits `tokens` into runs of the same language, falling back to `fallback` outside of any
+    /// declared language. The runs are in the order of the text, so that corrections are asked
+    /// for in that order too.
+    pub fn group(&self, tokens: Vec<Token>, fallback: Option<&str>) -> Vec<(Option<String>, Vec<Token>)> {
+        let mut groups: Vec<(Option<String>, Vec<Token>)> = Vec::new();
+        for token in tokens {
+            let lang: Option<String> = self.language_at(token.start).or(fallback).map(str::to_string);
+            match groups.last_mut() {
+                Some((group_lang, group)) if *group_lang == lang => group.push(token),
+                _ => groups.push((lang, vec![token])),
+            }
+        }
+
+        groups
+    }
+}
+
+/// The spell checkers of every language a run comes across. The one for the language of the run
+/// is loaded up front and the others the first time a document needs them.
+pub struct Languages {
+    config: Config,
+    /// The configuration for the language of the run, from `--lang` or the configuration.
+    run_config: Config,
+    default: Arc<SpellCheck>,
+    /// Spell checkers by normalized language name; `None` for languages checked by `default`.
+    loaded: Mutex<HashMap<String, Option<Arc<SpellCheck>>>>,
+}
+
+impl Languages {
+    /// Loads the spell checker for `lang`, or for the language of `config` if there is none.
+    pub fn new(config: &Config, lang: Option<&str>) -> Result<Self> {
+        let run_config: Config = config.for_language(lang)?;
+
+        Ok(Self {
+            config: config.clone(),
+            default: Arc::new(commands::load_spell_check(&run_config)?),
+            run_config,
+            loaded: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// The spell checker for `lang`. Languages without a profile or dictionary are checked in
+    /// the language of the run, with a warning the first time.
+    pub fn get(&self, lang: Option<&str>) -> Result<Arc<SpellCheck>> {
+        let Some(lang) = lang else {
+            return Ok(Arc::clone(&self.default));
+        };
+
+        let mut loaded = self.loaded.lock().map_err(|_| "The loaded languages were poisoned")?;
+        let key: String = config::normalize_language(lang);
+        if !loaded.contains_key(&key) {
+            let spell_check: Option<Arc<SpellCheck>> = match self.config.for_language(Some(lang)) {
+                Ok(config) if config.dictionaries == self.run_config.dictionaries && config.words == self.run_config.words => None,
+                Ok(config) => Some(Arc::new(commands::load_spell_check(&config)?)),
+                Err(err) => {
+                    eprintln!("Warning: {}; checking it like the rest", err);
+                    None
+                }
+            };
+            loaded.insert(key.clone(), spell_check);
+        }
+
+        Ok(loaded[&key].clone().unwrap_or_else(|| Arc::clone(&self.default)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Declared;
+    use crate::{
+        processor::Token,
+        syntax::{Options, Syntax},
+    };
+
+    #[test]
+    fn test_declared() {
+        let text: &str = "---\ntitle: Notes\nlang: de\n---\nHallo <span lang=\"en\">hello <b>world</b></span> Welt\n";
+        let declared: Declared = Declared::find(Syntax::Markdown, text);
+
+        assert_eq!(declared.document.as_deref(), Some("de"));
+        assert_eq!(declared.language_at(text.find("Hallo").unwrap()), Some("de"));
+        assert_eq!(declared.language_at(text.find("world").unwrap()), Some("en"));
+        assert_eq!(declared.language_at(text.find("Welt").unwrap()), Some("de"));
+
+        let html: &str = "<html lang='es'><p>hola</p><p xml:lang=\"fr\">salut<br>monde</p><img src=x lang=en>adiós</html>";
+        let declared: Declared = Declared::find(Syntax::Html, html);
+        assert_eq!(declared.language_at(html.find("hola").unwrap()), Some("es"));
+        assert_eq!(declared.language_at(html.find("monde").unwrap()), Some("fr"));
+        assert_eq!(declared.language_at(html.find("adiós").unwrap()), Some("es"));
+        assert_eq!(Declared::find(Syntax::Text, "lang: de").language_at(0), None);
+
+        let tokens: Vec<Token> = Syntax::Html.tokenize(html, &Options::default());
+        assert_eq!(tokens.len(), 4);
+        let groups: Vec<(Option<String>, Vec<Token>)> = Declared::default().group(tokens.clone(), Some("en"));
+        assert_eq!(groups.len(), 1);
+        let groups: Vec<(Option<String>, usize)> = declared
+            .group(tokens, None)
+            .into_iter()
+            .map(|(lang, tokens)| (lang, tokens.len()))
+            .collect();
+        assert_eq!(
+            groups,
+            vec![(Some("es".to_string()), 1), (Some("fr".to_string()), 2), (Some("es".to_string()), 1)]
+        );
+    }
+}
@@ -0,0 +1,416 @@
+use std::{collections::HashMap, error::Error, path::PathBuf};
+
+use lsp_server::{Connection, Message, Notification, Request, Response};
+use lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, CodeActionParams, CodeActionProviderCapability, Command, Diagnostic,
+    DiagnosticSeverity, DidChangeTextDocumentParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
+    ExecuteCommandOptions, ExecuteCommandParams, Position, PublishDiagnosticsParams, Range, ServerCapabilities,
+    TextDocumentContentChangeEvent, TextDocumentSyncCapability, TextDocumentSyncKind, TextEdit, Url, WorkspaceEdit,
+};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+use crate::{
+    cache,
+    cmd::{self, CmdData, Input},
+    commands,
+    directives::Directives,
+    pipeline,
+    processor::{LineIndex, Token},
+    spell_check::{self, SpellCheck},
+    syntax::Syntax,
+};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// The source diagnostics are published under.
+const SOURCE: &str = "spell_sweep";
+
+/// The command the "add to dictionary" code actions run, with the word and `user` or `project`.
+const ADD_WORD_COMMAND: &str = "spell_sweep.addWord";
+
+struct Document {
+    text: String,
+    syntax: Syntax,
+}
+
+struct Server<'a> {
+    spell_check: SpellCheck,
+    cmd_data: &'a CmdData,
+    documents: HashMap<Url, Document>,
+    /// Ranked suggestions of every misspelled word seen so far, lowercased, so that a change only
+    /// looks up the words that are new.
+    suggestions: HashMap<String, Vec<String>>,
+    /// The personal word list, shared by every project.
+    user_words: Option<PathBuf>,
+    /// The first word list of the project configuration, if it has one.
+    project_words: Option<PathBuf>,
+}
+
+/// Serves diagnostics over stdio until the client shuts the server down. The dictionary is loaded
+/// once and shared by every document.
+pub fn serve(spell_check: SpellCheck, cmd_data: &CmdData) -> Result<i32> {
+    let (connection, io_threads) = Connection::stdio();
+    let capabilities: ServerCapabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::INCREMENTAL)),
+        code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+        execute_command_provider: Some(ExecuteCommandOptions {
+            commands: vec![ADD_WORD_COMMAND.to_string()],
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+
+    let user_words: Option<PathBuf> = cache::personal_words();
+    let mut server: Server = Server {
+        spell_check,
+        cmd_data,
+        documents: HashMap::new(),
+        suggestions: HashMap::new(),
+        project_words: cmd_data.config.words.iter().find(|path| Some(*path) != user_words.as_ref()).cloned(),
+        user_words,
+    };
+
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    break;
+                }
+                server.handle_request(&connection, request)?;
+            }
+            Message::Notification(notification) => server.handle_notification(&connection, notification)?,
+            Message::Response(_) => {}
+        }
+    }
+    // The writer thread only stops once every sender is gone.
+    drop(connection);
+    io_threads.join()?;
+
+    Ok(cmd::EXIT_CLEAN)
+}
+
+impl Server<'_> {
+    fn handle_request(&mut self, connection: &Connection, request: Request) -> Result<()> {
+        let response: Response = match request.method.as_str() {
+            "textDocument/codeAction" => match serde_json::from_value::<CodeActionParams>(request.params) {
+                Ok(params) => Response::new_ok(request.id, self.code_actions(&params)),
+                Err(err) => Response::new_err(request.id, lsp_server::ErrorCode::InvalidParams as i32, err.to_string()),
+            },
+            "workspace/executeCommand" => match serde_json::from_value::<ExecuteCommandParams>(request.params) {
+                Ok(params) => match self.execute_command(&params) {
+                    Ok(()) => {
+                        self.publish_all(connection)?;
+                        Response::new_ok(request.id, Value::Null)
+                    }
+                    Err(err) => Response::new_err(request.id, lsp_server::ErrorCode::RequestFailed as i32, err.to_string()),
+                },
+                Err(err) => Response::new_err(request.id, lsp_server::ErrorCode::InvalidParams as i32, err.to_string()),
+            },
+            _ => Response::new_err(
+                request.id,
+                lsp_server::ErrorCode::MethodNotFound as i32,
+                format!("Unsupported request {}", request.method),
+            ),
+        };
+        connection.sender.send(Message::Response(response))?;
+
+        Ok(())
+    }
+
+    fn handle_notification(&mut self, connection: &Connection, notification: Notification) -> Result<()> {
+        match notification.method.as_str() {
+            "textDocument/didOpen" => {
+                let Some(params) = notification_params::<DidOpenTextDocumentParams>(&notification) else {
+                    return Ok(());
+                };
+                let document: Document = Document {
+                    syntax: self.syntax_of(&params.text_document.uri, &params.text_document.language_id),
+                    text: params.text_document.text,
+                };
+                self.documents.insert(params.text_document.uri.clone(), document);
+                self.publish(connection, &params.text_document.uri)?;
+            }
+            "textDocument/didChange" => {
+                let Some(params) = notification_params::<DidChangeTextDocumentParams>(&notification) else {
+                    return Ok(());
+                };
+                if let Some(document) = self.documents.get_mut(&params.text_document.uri) {
+                    for change in params.content_changes.iter() {
+                        apply_change(&mut document.text, change);
+                    }
+                    self.publish(connection, &params.text_document.uri)?;
+                }
+            }
+            "textDocument/didClose" => {
+                let Some(params) = notification_params::<DidCloseTextDocumentParams>(&notification) else {
+                    return Ok(());
+                };
+                self.documents.remove(&params.text_document.uri);
+                send_diagnostics(connection, params.text_document.uri, Vec::new())?;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Picks the syntax of a document from its path like the CLI does, falling back to the
+    /// language the editor reports for files without a known extension.
+    fn syntax_of(&self, uri: &Url, language_id: &str) -> Syntax {
+        let from_path: Syntax = match uri.to_file_path() {
+            Ok(path) => self.cmd_data.syntax_of(&Input::File(path)),
+            Err(_) => Syntax::Text,
+        };
+        if from_path != Syntax::Text || self.cmd_data.syntax.is_some() {
+            return from_path;
+        }
+
+        let syntax_name: &str = match language_id {
+            "shellscript" => "shell",
+            "javascriptreact" | "typescript" | "typescriptreact" => "javascript",
+            "cpp" | "objective-c" => "c",
+            "tex" => "latex",
+            language_id => language_id,
+        };
+        cmd::parse_syntax(syntax_name).unwrap_or(Syntax::Text)
+    }
+
+    fn publish(&mut self, connection: &Connection, uri: &Url) -> Result<()> {
+        let diagnostics: Vec<Diagnostic> = self.diagnostics(uri);
+        send_diagnostics(connection, uri.clone(), diagnostics)
+    }
+
+    fn publish_all(&mut self, connection: &Connection) -> Result<()> {
+        let uris: Vec<Url> = self.documents.keys().cloned().collect();
+        for uri in uris.iter() {
+            self.publish(connection, uri)?;
+        }
+
+        Ok(())
+    }
+
+    fn diagnostics(&mut self, uri: &Url) -> Vec<Diagnostic> {
+        let Some(document) = self.documents.get(uri) else {
+            return Vec::new();
+        };
+        let tokens: Vec<Token> = pipeline::tokenize(self.cmd_data, document.syntax, &document.text, &mut Directives::default());
+        let line_index: LineIndex = LineIndex::new(&document.text);
+
+        self.spell_check
+            .misspelled(tokens)
+            .into_iter()
+            .map(|token| {
+                let spell_check: &SpellCheck = &self.spell_check;
+                let suggestions: Vec<String> = self
+                    .suggestions
+                    .entry(token.word.to_lowercase())
+                    .or_insert_with(|| spell_check.suggest(&token.word).into_iter().map(|(suggestion, _)| suggestion).collect())
+                    .iter()
+                    .map(|suggestion| spell_check::convert_case(suggestion, &token.word))
+                    .collect();
+                let message: String = match suggestions.is_empty() {
+                    true => format!("Unknown word \"{}\"", token.word),
+                    false => format!("Unknown word \"{}\"; did you mean {}?", token.word, suggestions.join(", ")),
+                };
+
+                Diagnostic {
+                    range: Range::new(
+                        position(&document.text, &line_index, token.start),
+                        position(&document.text, &line_index, token.end),
+                    ),
+                    severity: Some(DiagnosticSeverity::INFORMATION),
+                    source: Some(SOURCE.to_string()),
+                    message,
+                    data: Some(json!({ "word": token.word, "suggestions": suggestions })),
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+
+    /// Offers to replace each misspelling in the range with each of its suggestions, best first,
+    /// and to add the word to the user or project word list.
+    fn code_actions(&self, params: &CodeActionParams) -> Vec<CodeActionOrCommand> {
+        let uri: &Url = &params.text_document.uri;
+        let Some(document) = self.documents.get(uri) else {
+            return Vec::new();
+        };
+        let mut actions: Vec<CodeActionOrCommand> = Vec::new();
+
+        for diagnostic in params.context.diagnostics.iter().filter(|diagnostic| diagnostic.source.as_deref() == Some(SOURCE)) {
+            let Some(data) = diagnostic.data.as_ref() else {
+                continue;
+            };
+            let word: &str = data["word"].as_str().unwrap_or_default();
+            let start: usize = offset(&document.text, diagnostic.range.start);
+            let end: usize = offset(&document.text, diagnostic.range.end);
+            let original: &str = &document.text[start..end.max(start)];
+
+            for (rank, suggestion) in data["suggestions"].as_array().into_iter().flatten().filter_map(Value::as_str).enumerate() {
+                let edit: TextEdit = TextEdit::new(diagnostic.range, document.syntax.encode(original, suggestion));
+                actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                    title: format!("Replace with \"{}\"", suggestion),
+                    kind: Some(CodeActionKind::QUICKFIX),
+                    diagnostics: Some(vec![diagnostic.clone()]),
+                    edit: Some(WorkspaceEdit {
+                        changes: Some(HashMap::from([(uri.clone(), vec![edit])])),
+                        ..Default::default()
+                    }),
+                    is_preferred: Some(rank == 0),
+                    ..Default::default()
+                }));
+            }
+
+            for (target, list) in [("user", &self.user_words), ("project", &self.project_words)] {
+                if list.is_none() {
+                    continue;
+                }
+                actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                    title: format!("Add \"{}\" to the {} dictionary", word, target),
+                    kind: Some(CodeActionKind::QUICKFIX),
+                    diagnostics: Some(vec![diagnostic.clone()]),
+                    command: Some(Command::new(
+                        format!("Add \"{}\" to the {} dictionary", word, target),
+                        ADD_WORD_COMMAND.to_string(),
+                        Some(vec![json!(word), json!(target)]),
+                    )),
+                    ..Default::default()
+                }));
+            }
+        }
+
+        actions
+    }
+
+    fn execute_command(&mut self, params: &ExecuteCommandParams) -> Result<()> {
+        let (Some(word), target) = (
+            params.arguments.first().and_then(Value::as_str),
+            params.arguments.get(1).and_then(Value::as_str),
+        ) else {
+            return Err(format!("{} needs the word to add", ADD_WORD_COMMAND).into());
+        };
+        let list: &PathBuf = match target {
+            Some("project") => self.project_words.as_ref(),
+            _ => self.user_words.as_ref(),
+        }
+        .ok_or("There is no word list to add to")?;
+
+        let words: Vec<String> = vec![word.to_string()];
+        commands::append_words(&words, list)?;
+        self.spell_check.add_words(words);
+
+        Ok(())
+    }
+}
+
+/// Reads the params of `notification`. Notifications cannot be answered with an error, so params
+/// that do not fit are logged and the notification is skipped rather than stopping the server.
+fn notification_params<T: DeserializeOwned>(notification: &Notification) -> Option<T> {
+    serde_json::from_value(notification.params.clone())
+        .map_err(|err| eprintln!("Error: Invalid params for {}: {}", notification.method, err))
+        .ok()
+}
+
+fn send_diagnostics(connection: &Connection, uri: Url, diagnostics: Vec<Diagnostic>) -> Result<()> {
+    let params: PublishDiagnosticsParams = PublishDiagnosticsParams::new(uri, diagnostics, None);
+    connection.sender.send(Message::Notification(Notification::new(
+        "textDocument/publishDiagnostics".to_string(),
+        params,
+    )))?;
+
+    Ok(())
+}
+
+/// Returns the position of the byte at `offset`, with the character counted in UTF-16 code units
+/// as LSP clients expect by default.
+fn position(text: &str, line_index: &LineIndex, offset: usize) -> Position {
+    let line: usize = line_index.line_starts.partition_point(|&start| start <= offset) - 1;
+    let line_start: usize = line_index.line_starts[line];
+
+    Position::new(line as u32, text[line_start..offset].encode_utf16().count() as u32)
+}
+
+/// Returns the byte offset of `position`, clamped to the end of its line.
+fn offset(text: &str, position: Position) -> usize {
+    let line_start: usize = text.split_inclusive('\n').take(position.line as usize).map(str::len).sum();
+    let mut units: u32 = 0;
+
+    for (idx, c) in text[line_start..].char_indices() {
+        if units >= position.character || c == '\n' {
+            return line_start + idx;
+        }
+        units += c.len_utf16() as u32;
+    }
+    text.len()
+}
+
+/// Applies an incremental change, or replaces the whole text if the change has no range.
+fn apply_change(text: &mut String, change: &TextDocumentContentChangeEvent) {
+    match change.range {
+        Some(range) => {
+            let start: usize = offset(text, range.start);
+            let end: usize = offset(text, range.end).max(start);
+            text.replace_range(start..end, &change.text);
+        }
+        None => *text = change.text.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_server::Notification;
+    use lsp_types::{DidOpenTextDocumentParams, Position, Range, TextDocumentContentChangeEvent};
+    use serde_json::json;
+
+    use super::{apply_change, notification_params, offset, position};
+    use crate::processor::LineIndex;
+
+    #[test]
+    fn test_positions() {
+        let text: &str = "naïve 😀 helo\nwrold";
+        let line_index: LineIndex = LineIndex::new(text);
+
+        let helo: usize = text.find("helo").unwrap();
+        assert_eq!(position(text, &line_index, helo), Position::new(0, 9));
+        assert_eq!(offset(text, Position::new(0, 9)), helo);
+        assert_eq!(position(text, &line_index, text.len()), Position::new(1, 5));
+        assert_eq!(offset(text, Position::new(1, 2)), text.find("old").unwrap());
+        assert_eq!(offset(text, Position::new(0, 99)), text.find('\n').unwrap());
+    }
+
+    #[test]
+    fn test_apply_change() {
+        let mut text: String = "helo\nwrold\n".to_string();
+        apply_change(
+            &mut text,
+            &TextDocumentContentChangeEvent {
+                range: Some(Range::new(Position::new(1, 1), Position::new(1, 3))),
+                range_length: None,
+                text: "or".to_string(),
+            },
+        );
+        assert_eq!(text, "helo\nworld\n");
+
+        apply_change(
+            &mut text,
+            &TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text: "hello".to_string(),
+            },
+        );
+        assert_eq!(text, "hello");
+    }
+
+    #[test]
+    fn test_notification_params() {
+        let open = |params| Notification::new("textDocument/didOpen".to_string(), params);
+        let document = json!({ "uri": "file:///notes.md", "languageId": "markdown", "version": 1, "text": "helo" });
+
+        let params: Option<DidOpenTextDocumentParams> = notification_params(&open(json!({ "textDocument": document })));
+        assert_eq!(params.map(|params| params.text_document.text), Some("helo".to_string()));
+        assert!(notification_params::<DidOpenTextDocumentParams>(&open(json!({ "textDocument": 1 }))).is_none());
+    }
+}
@@ -1,31 +1,59 @@
+mod baseline;
 mod bk_tree;
 mod bloom_filter;
+mod cache;
 mod cmd;
+mod commands;
+mod config;
+mod daemon;
 mod dictionary;
+mod directives;
+mod hunspell;
+mod ispell;
+mod language;
+mod lsp;
+mod output;
+mod pipeline;
 mod processor;
+mod prompt;
+mod rewrite;
 mod spell_check;
+mod syntax;
 mod utils;
+mod variants;
+mod walk;
 
-use std::process;
+use std::{error::Error, process};
 
-use spell_check::SpellCheck;
+use cmd::{Cli, Subcommand, EXIT_ERROR};
+use config::Config;
+use language::Languages;
+
+fn run(cli: &Cli) -> Result<i32, Box<dyn Error>> {
+    // The configuration of the language picked with --lang; documents of check can switch to others.
+    let config: Config = cli.config.for_language(cli.lang.as_deref())?;
+
+    match &cli.subcommand {
+        Subcommand::Check(cmd_data) => pipeline::run(&Languages::new(&cli.config, cli.lang.as_deref())?, cmd_data),
+        Subcommand::Suggest { words, format } => commands::suggest(&commands::load_spell_check(&config)?, words, *format),
+        Subcommand::Build { force } => commands::build(&config, *force),
+        Subcommand::Add { words, list } => commands::add(&config, words, list),
+        Subcommand::Serve => daemon::serve(&config),
+        Subcommand::Stats { format } => commands::stats(&config, *format),
+        Subcommand::Lsp(cmd_data) => lsp::serve(commands::load_spell_check(&config)?, cmd_data),
+        Subcommand::Pipe(cmd_data) => ispell::serve(commands::load_spell_check(&config)?, cmd_data),
+    }
+}
 
 fn main() {
-    let cmd_data = cmd::parse_cmd_args().unwrap_or_else(|err| {
+    let cli: Cli = cmd::parse_cmd_args().unwrap_or_else(|err| {
         eprintln!("Error: {}", err);
-        process::exit(1);
+        process::exit(EXIT_ERROR);
     });
 
-    let bk_tree_path: &str = "bk_tree.bin";
-    let bloom_filter_path: &str = "bloom_filter.bin";
-    let dictionary_path: &str = "dictionary.txt";
-    let alphabet_length: u16 = 255;
-
-    let spell_check: SpellCheck = SpellCheck::new(
-        bk_tree_path,
-        bloom_filter_path,
-        dictionary_path,
-        alphabet_length,
-    );
-    spell_check.run(cmd_data);
+    let status: i32 = run(&cli).unwrap_or_else(|err| {
+        eprintln!("Error: {}", err);
+        process::exit(EXIT_ERROR);
+    });
+    process::exit(status);
 }
@@ -0,0 +1,249 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::spell_check::Misspelling;
+
+/// How findings are printed in report mode.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+    Jsonl,
+    Sarif,
+    Checkstyle,
+}
+
+#[derive(Serialize)]
+struct Span {
+    start: usize,
+    end: usize,
+}
+
+#[derive(Serialize)]
+struct Finding<'a> {
+    file: &'a str,
+    line: usize,
+    column: usize,
+    span: Span,
+    word: &'a str,
+    suggestions: &'a [String],
+}
+
+impl<'a> Finding<'a> {
+    fn new(file: &'a str, misspelling: &'a Misspelling) -> Self {
+        Self {
+            file,
+            line: misspelling.line,
+            column: misspelling.column,
+            span: Span {
+                start: misspelling.start,
+                end: misspelling.end,
+            },
+            word: &misspelling.word,
+            suggestions: &misspelling.suggestions,
+        }
+    }
+}
+
+/// Renders the findings of every checked file, given as `(file name, misspellings)` pairs.
+pub fn render(format: Format, reports: &[(String, Vec<Misspelling>)]) -> String {
+    let findings: Vec<Finding> = reports
+        .iter()
+        .flat_map(|(file, misspellings)| misspellings.iter().map(move |misspelling| Finding::new(file, misspelling)))
+        .collect();
+
+    match format {
+        Format::Text => render_text(&findings),
+        Format::Json => format!("{}\n", serde_json::to_string_pretty(&findings).expect("Failed to serialize findings")),
+        Format::Jsonl => render_jsonl(&findings),
+        Format::Sarif => format!("{}\n", serde_json::to_string_pretty(&sarif_log(&findings)).expect("Failed to serialize SARIF log")),
+        Format::Checkstyle => render_checkstyle(reports),
+    }
+}
+
+fn render_text(findings: &[Finding]) -> String {
+    findings
+        .iter()
+        .map(|finding| {
+            format!(
+                "{}:{}:{}: {}: {}\n",
+                finding.file,
+                finding.line,
+                finding.column,
+                finding.word,
+                finding.suggestions.join(", ")
+            )
+        })
+        .collect()
+}
+
+fn render_jsonl(findings: &[Finding]) -> String {
+    findings
+        .iter()
+        .map(|finding| format!("{}\n", serde_json::to_string(finding).expect("Failed to serialize finding")))
+        .collect()
+}
+
+fn message(finding: &Finding) -> String {
+    match finding.suggestions.is_empty() {
+        true => format!("\"{}\" is misspelled", finding.word),
+        false => format!("\"{}\" is misspelled, did you mean {}?", finding.word, finding.suggestions.join(", ")),
+    }
+}
+
+fn sarif_log(findings: &[Finding]) -> Value {
+    let results: Vec<Value> = findings
+        .iter()
+        .map(|finding| {
+            json!({
+                "ruleId": "misspelling",
+                "level": "warning",
+                "message": { "text": message(finding) },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": finding.file },
+                        "region": {
+                            "startLine": finding.line,
+                            "startColumn": finding.column,
+                            "endColumn": finding.column + finding.word.chars().count(),
+                            "byteOffset": finding.span.start,
+                            "byteLength": finding.span.end - finding.span.start,
+                        },
+                    },
+                }],
+                "fixes": finding.suggestions.iter().take(1).map(|suggestion| json!({
+                    "description": { "text": format!("Replace with \"{}\"", suggestion) },
+                    "artifactChanges": [{
+                        "artifactLocation": { "uri": finding.file },
+                        "replacements": [{
+                            "deletedRegion": {
+                                "byteOffset": finding.span.start,
+                                "byteLength": finding.span.end - finding.span.start,
+                            },
+                            "insertedContent": { "text": suggestion },
+                        }],
+                    }],
+                })).collect::<Vec<Value>>(),
+                "properties": { "suggestions": finding.suggestions },
+            })
+        })
+        .collect();
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": [{
+                        "id": "misspelling",
+                        "shortDescription": { "text": "Misspelled word" },
+                    }],
+                },
+            },
+            "columnKind": "unicodeCodePoints",
+            "results": results,
+        }],
+    })
+}
+
+fn escape_xml(string: &str) -> String {
+    let mut escaped: String = String::with_capacity(string.len());
+    for c in string.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn render_checkstyle(reports: &[(String, Vec<Misspelling>)]) -> String {
+    let mut xml: String = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<checkstyle version=\"4.3\">\n");
+
+    for (file, misspellings) in reports {
+        xml.push_str(&format!("  <file name=\"{}\">\n", escape_xml(file)));
+        for misspelling in misspellings {
+            let finding: Finding = Finding::new(file, misspelling);
+            xml.push_str(&format!(
+                "    <error line=\"{}\" column=\"{}\" severity=\"warning\" message=\"{}\" source=\"{}.misspelling\"/>\n",
+                finding.line,
+                finding.column,
+                escape_xml(&message(&finding)),
+                env!("CARGO_PKG_NAME")
+            ));
+        }
+        xml.push_str("  </file>\n");
+    }
+    xml.push_str("</checkstyle>\n");
+
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::{render, Format};
+    use crate::{dictionary::Dictionary, processor, spell_check::{Misspelling, SpellCheck}};
+
+    fn misspellings() -> Vec<(String, Vec<Misspelling>)> {
+        let words: Vec<String> = vec!["hello".to_string(), "help".to_string(), "notes".to_string()];
+        let spell_check: SpellCheck = SpellCheck::from(&Dictionary::from((words, 255)));
+
+        vec![("notes.txt".to_string(), spell_check.misspelled_tokens("Notes\nHelo.", processor::tokenize("Notes\nHelo.", processor::NON_WORDS), 0, 0))]
+    }
+
+    #[test]
+    fn test_render_text() {
+        let rendered: String = render(Format::Text, &misspellings());
+        assert_eq!(rendered, "notes.txt:2:1: Helo: Help, Hello\n");
+    }
+
+    #[test]
+    fn test_render_json() {
+        let rendered: String = render(Format::Jsonl, &misspellings());
+        let finding: Value = serde_json::from_str(rendered.trim()).expect("Invalid JSON");
+
+        assert_eq!(finding["file"], "notes.txt");
+        assert_eq!(finding["line"], 2);
+        assert_eq!(finding["span"]["start"], 6);
+        assert_eq!(finding["span"]["end"], 10);
+        assert_eq!(finding["suggestions"][0], "Help");
+
+        let rendered: String = render(Format::Json, &misspellings());
+        let findings: Value = serde_json::from_str(&rendered).expect("Invalid JSON");
+        assert_eq!(findings[0], finding);
+    }
+
+    #[test]
+    fn test_render_sarif() {
+        let rendered: String = render(Format::Sarif, &misspellings());
+        let sarif: Value = serde_json::from_str(&rendered).expect("Invalid JSON");
+        let result: &Value = &sarif["runs"][0]["results"][0];
+
+        assert_eq!(sarif["version"], "2.1.0");
+        assert_eq!(result["locations"][0]["physicalLocation"]["region"]["startLine"], 2);
+        assert_eq!(result["locations"][0]["physicalLocation"]["region"]["endColumn"], 5);
+        assert_eq!(result["properties"]["suggestions"][1], "Hello");
+    }
+
+    #[test]
+    fn test_render_checkstyle() {
+        let mut reports: Vec<(String, Vec<Misspelling>)> = misspellings();
+        reports[0].0 = "a&b.txt".to_string();
+        reports.push(("clean.txt".to_string(), Vec::new()));
+        let rendered: String = render(Format::Checkstyle, &reports);
+
+        assert!(rendered.contains("<file name=\"a&amp;b.txt\">"));
+        assert!(rendered.contains("<file name=\"clean.txt\">\n  </file>"));
+        assert!(rendered.contains("line=\"2\" column=\"1\""));
+        assert!(rendered.contains("message=\"&quot;Helo&quot; is misspelled, did you mean Help, Hello?\""));
+    }
+}
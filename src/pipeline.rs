@@ -0,0 +1,406 @@
+use std::{
+    collections::HashSet,
+    error::Error,
+    io::{self, BufRead, Write},
+    sync::Arc,
+};
+
+use rayon::prelude::*;
+
+use crate::{
+    baseline::Baseline,
+    cmd::{CmdData, Input, EXIT_CLEAN, EXIT_ERROR, EXIT_MISSPELLED},
+    directives::Directives,
+    language::{Declared, Languages},
+    output::{self, Format},
+    processor::{self, Token},
+    prompt::Prompter,
+    rewrite::{self, Destination},
+    spell_check::{self, Misspelling, Mode, SpellCheck},
+    syntax::Syntax,
+};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// How input that is not valid UTF-8 is read.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Decoding {
+    /// Invalid bytes become U+FFFD, which is fine for reporting findings.
+    Lossy,
+    /// Invalid bytes are an error, since writing the text back would replace bytes that no
+    /// correction touched.
+    Strict,
+}
+
+/// Calls `f` with every line of `reader`, including its line terminator, along with the
+/// zero-based line number and the byte offset the line starts at. Only one line is held in
+/// memory at a time.
+fn for_each_line<R, F>(mut reader: R, decoding: Decoding, mut f: F) -> Result<()>
+where
+    R: BufRead,
+    F: FnMut(&str, usize, usize) -> Result<()>,
+{
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut line_number: usize = 0;
+    let mut byte_offset: usize = 0;
+
+    loop {
+        buffer.clear();
+        let read: usize = reader.read_until(b'\n', &mut buffer)?;
+        if read == 0 {
+            break;
+        }
+
+        let line = match decoding {
+            Decoding::Lossy => String::from_utf8_lossy(&buffer),
+            Decoding::Strict => std::str::from_utf8(&buffer)
+                .map_err(|err| not_utf8(byte_offset + err.valid_up_to()))?
+                .into(),
+        };
+        f(&line, line_number, byte_offset)?;
+        line_number += 1;
+        byte_offset += read;
+    }
+
+    Ok(())
+}
+
+fn not_utf8(byte_offset: usize) -> String {
+    format!("Not valid UTF-8 at byte {}; only UTF-8 text can be corrected without changing other bytes", byte_offset)
+}
+
+/// Reports every failed input on stderr and turns them into a single error, so one unreadable
+/// file does not stop the others from being checked.
+fn check_failures(failures: Vec<(String, String)>) -> Result<()> {
+    for (file_name, err) in failures.iter() {
+        eprintln!("Error: {}: {}", file_name, err);
+    }
+
+    match failures.len() {
+        0 => Ok(()),
+        count => Err(format!("{} input(s) could not be checked", count).into()),
+    }
+}
+
+/// Whether `input` can be checked a line at a time. Checking for consistent spelling needs the
+/// whole document at once.
+fn is_line_based(cmd_data: &CmdData, input: &Input) -> bool {
+    cmd_data.syntax_of(input).is_line_based() && !cmd_data.config.consistent
+}
+
+/// Finds the words of `text` to check, splitting identifiers into subwords in identifier mode
+/// and leaving out what inline directives exclude.
+pub fn tokenize(cmd_data: &CmdData, syntax: Syntax, text: &str, directives: &mut Directives) -> Vec<Token> {
+    let mut tokens: Vec<Token> = syntax.tokenize(text, &cmd_data.syntax_options);
+    if cmd_data.syntax_options.identifiers {
+        tokens = processor::split_identifiers(tokens)
+            .into_iter()
+            .filter(|token| !cmd_data.abbreviations.contains(&token.word.to_lowercase()))
+            .collect();
+    }
+
+    directives.filter(text, tokens)
+}
+
+pub fn run(languages: &Languages, cmd_data: &CmdData) -> Result<i32> {
+    let status: i32 = match (cmd_data.update_baseline, cmd_data.mode) {
+        (true, _) => update_baseline(languages, cmd_data)?,
+        (false, Mode::Interactive) => interactive(languages, cmd_data)?,
+        (false, Mode::Fix { unambiguous }) => fix(languages, cmd_data, unambiguous)?,
+        (false, Mode::Report) => report(languages, cmd_data)?,
+        (false, Mode::List) => list(languages, cmd_data)?,
+    };
+
+    // The files that could not be walked were reported already, but were not checked.
+    match cmd_data.walk_failed {
+        true => Ok(EXIT_ERROR),
+        false => Ok(status),
+    }
+}
+
+fn correct<F>(languages: &Languages, cmd_data: &CmdData, input: &Input, out: &mut dyn Write, mut choose: F) -> Result<()>
+where
+    F: FnMut(&str, &[(String, u8)]) -> Option<String>,
+{
+    let destination: &Destination = &cmd_data.destination;
+    let syntax: Syntax = cmd_data.syntax_of(input);
+    let fallback: Option<&str> = cmd_data.language_of(input);
+
+    if is_line_based(cmd_data, input) && destination.is_streaming() {
+        let spell_check: Arc<SpellCheck> = languages.get(fallback)?;
+        // The input is opened first, so an input that cannot be read leaves no empty output behind.
+        let reader: Box<dyn BufRead> = input.open()?;
+        let mut writer: Box<dyn Write> = destination.stream(out)?;
+        let mut directives: Directives = Directives::default();
+        for_each_line(reader, Decoding::Strict, |line, _, _| {
+            let tokens: Vec<Token> = tokenize(cmd_data, syntax, line, &mut directives);
+            writer.write_all(spell_check.correct_tokens(line, tokens, &mut choose).as_bytes())?;
+            Ok(())
+        })?;
+        writer.flush()?;
+        return Ok(());
+    }
+
+    let original: String = String::from_utf8(input.read_to_end()?).map_err(|err| not_utf8(err.utf8_error().valid_up_to()))?;
+    let tokens: Vec<Token> = tokenize(cmd_data, syntax, &original, &mut Directives::default());
+    let mut replacements: Vec<(usize, usize, String)> = Vec::new();
+    for (lang, tokens) in Declared::find(syntax, &original).group(tokens.clone(), fallback) {
+        let spell_check: Arc<SpellCheck> = languages.get(lang.as_deref())?;
+        replacements.extend(
+            spell_check
+                .corrections(tokens, &mut choose)
+                .into_iter()
+                .map(|(start, end, replacement)| (start, end, syntax.encode(&original[start..end], &replacement))),
+        );
+    }
+    if cmd_data.config.consistent {
+        let corrected: HashSet<usize> = replacements.iter().map(|(start, _, _)| *start).collect();
+        let remaining: Vec<Token> = tokens.into_iter().filter(|token| !corrected.contains(&token.start)).collect();
+        replacements.extend(
+            spell_check::consistency_corrections(remaining, &mut choose)
+                .into_iter()
+                .map(|(start, end, replacement)| (start, end, syntax.encode(&original[start..end], &replacement))),
+        );
+        replacements.sort_by_key(|(start, _, _)| *start);
+    }
+    let corrected: String = processor::splice(&original, &replacements);
+
+    if destination.is_streaming() {
+        let mut writer: Box<dyn Write> = destination.stream(out)?;
+        writer.write_all(corrected.as_bytes())?;
+        writer.flush()?;
+    } else {
+        rewrite::emit(destination, out, input.path(), &input.file_name(), &original, &corrected)?;
+    }
+
+    Ok(())
+}
+
+fn interactive(languages: &Languages, cmd_data: &CmdData) -> Result<i32> {
+    let mut prompter: Prompter = Prompter::open(cmd_data.answers.as_deref())?;
+    let mut failures: Vec<(String, String)> = Vec::new();
+
+    for input in cmd_data.inputs.iter() {
+        let mut stdout = io::stdout();
+        let choose = |word: &str, suggestions: &[(String, u8)]| prompter.choose(word, suggestions);
+        if let Err(err) = correct(languages, cmd_data, input, &mut stdout, choose) {
+            failures.push((input.file_name(), err.to_string()));
+        }
+    }
+    check_failures(failures)?;
+
+    Ok(EXIT_CLEAN)
+}
+
+fn fix(languages: &Languages, cmd_data: &CmdData, unambiguous: bool) -> Result<i32> {
+    let choose = |_: &str, suggestions: &[(String, u8)]| SpellCheck::pick_top_suggestion(suggestions, unambiguous);
+
+    if let [input] = cmd_data.inputs.as_slice() {
+        correct(languages, cmd_data, input, &mut io::stdout().lock(), choose)
+            .map_err(|err| format!("{}: {}", input.file_name(), err))?;
+        return Ok(EXIT_CLEAN);
+    }
+
+    // Files are corrected in parallel, each into its own buffer, and printed in input order.
+    let outputs: Vec<std::result::Result<Vec<u8>, String>> = cmd_data
+        .inputs
+        .par_iter()
+        .map(|input| {
+            let mut buffer: Vec<u8> = Vec::new();
+            correct(languages, cmd_data, input, &mut buffer, choose)
+                .map(|_| buffer)
+                .map_err(|err| err.to_string())
+        })
+        .collect();
+
+    let mut stdout = io::stdout().lock();
+    let mut failures: Vec<(String, String)> = Vec::new();
+    for (input, output) in cmd_data.inputs.iter().zip(outputs) {
+        match output {
+            Ok(buffer) => stdout.write_all(&buffer)?,
+            Err(err) => failures.push((input.file_name(), err)),
+        }
+    }
+    stdout.flush()?;
+    check_failures(failures)?;
+
+    Ok(EXIT_CLEAN)
+}
+
+fn collect_misspellings(languages: &Languages, cmd_data: &CmdData, input: &Input) -> Result<Vec<Misspelling>> {
+    let syntax: Syntax = cmd_data.syntax_of(input);
+    let fallback: Option<&str> = cmd_data.language_of(input);
+    if !is_line_based(cmd_data, input) {
+        let text: String = input.read_to_string()?;
+        let tokens: Vec<Token> = tokenize(cmd_data, syntax, &text, &mut Directives::default());
+        let mut misspellings: Vec<Misspelling> = Vec::new();
+        for (lang, tokens) in Declared::find(syntax, &text).group(tokens.clone(), fallback) {
+            misspellings.extend(languages.get(lang.as_deref())?.misspelled_tokens(&text, tokens, 0, 0));
+        }
+        if cmd_data.config.consistent {
+            // Words found misspelled already are not reported twice.
+            let found: HashSet<usize> = misspellings.iter().map(|misspelling| misspelling.start).collect();
+            misspellings.extend(
+                spell_check::inconsistencies(&text, tokens, 0, 0)
+                    .into_iter()
+                    .filter(|inconsistency| !found.contains(&inconsistency.start)),
+            );
+            misspellings.sort_by_key(|misspelling| misspelling.start);
+        }
+        return Ok(misspellings);
+    }
+
+    let spell_check: Arc<SpellCheck> = languages.get(fallback)?;
+    let mut misspellings: Vec<Misspelling> = Vec::new();
+    let mut directives: Directives = Directives::default();
+    for_each_line(input.open()?, Decoding::Lossy, |line, line_number, byte_offset| {
+        let tokens: Vec<Token> = tokenize(cmd_data, syntax, line, &mut directives);
+        misspellings.extend(spell_check.misspelled_tokens(line, tokens, line_number, byte_offset));
+        Ok(())
+    })?;
+
+    Ok(misspellings)
+}
+
+/// Checks every input in parallel, returning the findings per file in input order.
+fn check_all(languages: &Languages, cmd_data: &CmdData) -> Result<Vec<(String, Vec<Misspelling>)>> {
+    let results: Vec<(String, std::result::Result<Vec<Misspelling>, String>)> = cmd_data
+        .inputs
+        .par_iter()
+        .map(|input| {
+            let misspellings = collect_misspellings(languages, cmd_data, input).map_err(|err| err.to_string());
+            (input.file_name(), misspellings)
+        })
+        .collect();
+
+    let mut reports: Vec<(String, Vec<Misspelling>)> = Vec::new();
+    let mut failures: Vec<(String, String)> = Vec::new();
+    for (file_name, result) in results {
+        match result {
+            Ok(misspellings) => reports.push((file_name, misspellings)),
+            Err(err) => failures.push((file_name, err)),
+        }
+    }
+    check_failures(failures)?;
+
+    Ok(reports)
+}
+
+/// Loads the configured baseline, or an empty one that accepts nothing.
+fn load_baseline(cmd_data: &CmdData) -> Result<Baseline> {
+    match &cmd_data.config.baseline {
+        Some(path) => Baseline::from_file(path),
+        None => Ok(Baseline::default()),
+    }
+}
+
+/// Fails the check only when more findings are left than `--max-errors` allows.
+fn exit_status(cmd_data: &CmdData, found: usize) -> i32 {
+    match found > cmd_data.config.max_errors {
+        true => EXIT_MISSPELLED,
+        false => EXIT_CLEAN,
+    }
+}
+
+fn update_baseline(languages: &Languages, cmd_data: &CmdData) -> Result<i32> {
+    let path = cmd_data.config.baseline.as_ref().expect("--update-baseline requires a baseline file");
+    let mut baseline: Baseline = Baseline::new(path);
+    for (file_name, misspellings) in check_all(languages, cmd_data)? {
+        baseline.record(&file_name, &misspellings);
+    }
+    baseline.to_file(path)?;
+    println!("Recorded {} finding(s) in {}", baseline.len(), path.display());
+
+    Ok(EXIT_CLEAN)
+}
+
+fn report(languages: &Languages, cmd_data: &CmdData) -> Result<i32> {
+    let mut stdout = io::stdout().lock();
+    let mut baseline: Baseline = load_baseline(cmd_data)?;
+
+    // A single input in a line-oriented format is written as each line is checked; everything
+    // else is gathered first, since the findings of all files end up in one document.
+    if let ([input], Format::Text | Format::Jsonl, true) = (
+        cmd_data.inputs.as_slice(),
+        cmd_data.format,
+        cmd_data.inputs.first().is_some_and(|input| is_line_based(cmd_data, input)),
+    ) {
+        let file_name: String = input.file_name();
+        let spell_check: Arc<SpellCheck> = languages.get(cmd_data.language_of(input))?;
+        let syntax: Syntax = cmd_data.syntax_of(input);
+        let mut found: usize = 0;
+        let mut directives: Directives = Directives::default();
+        for_each_line(input.open()?, Decoding::Lossy, |line, line_number, byte_offset| {
+            let tokens: Vec<Token> = tokenize(cmd_data, syntax, line, &mut directives);
+            let misspellings: Vec<Misspelling> =
+                baseline.filter(&file_name, spell_check.misspelled_tokens(line, tokens, line_number, byte_offset));
+            found += misspellings.len();
+            stdout.write_all(output::render(cmd_data.format, &[(file_name.clone(), misspellings)]).as_bytes())?;
+            Ok(())
+        })?;
+        stdout.flush()?;
+        return Ok(exit_status(cmd_data, found));
+    }
+
+    let reports: Vec<(String, Vec<Misspelling>)> = check_all(languages, cmd_data)?
+        .into_iter()
+        .map(|(file_name, misspellings)| {
+            let misspellings: Vec<Misspelling> = baseline.filter(&file_name, misspellings);
+            (file_name, misspellings)
+        })
+        .collect();
+    stdout.write_all(output::render(cmd_data.format, &reports).as_bytes())?;
+    stdout.flush()?;
+
+    let found: usize = reports.iter().map(|(_, misspellings)| misspellings.len()).sum();
+    Ok(exit_status(cmd_data, found))
+}
+
+fn list(languages: &Languages, cmd_data: &CmdData) -> Result<i32> {
+    let mut stdout = io::stdout().lock();
+    let mut baseline: Baseline = load_baseline(cmd_data)?;
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut found: usize = 0;
+
+    for (file_name, misspellings) in check_all(languages, cmd_data)? {
+        for misspelling in baseline.filter(&file_name, misspellings) {
+            found += 1;
+            if seen.insert(misspelling.word.to_lowercase()) {
+                writeln!(stdout, "{}", misspelling.word)?;
+            }
+        }
+    }
+    stdout.flush()?;
+
+    Ok(exit_status(cmd_data, found))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{for_each_line, Decoding};
+
+    #[test]
+    fn test_for_each_line() {
+        let mut lines: Vec<(String, usize, usize)> = Vec::new();
+        for_each_line("first\r\nsecond\n\nlast".as_bytes(), Decoding::Lossy, |line, line_number, byte_offset| {
+            lines.push((line.to_string(), line_number, byte_offset));
+            Ok(())
+        })
+        .expect("Failed to read lines");
+
+        assert_eq!(
+            lines,
+            vec![
+                ("first\r\n".to_string(), 0, 0),
+                ("second\n".to_string(), 1, 7),
+                ("\n".to_string(), 2, 14),
+                ("last".to_string(), 3, 15),
+            ]
+        );
+
+        let latin1: &[u8] = b"ok\ncaf\xe9\n";
+        let err = for_each_line(latin1, Decoding::Strict, |_, _, _| Ok(())).expect_err("Latin-1 is not UTF-8");
+        assert!(err.to_string().starts_with("Not valid UTF-8 at byte 6"));
+        assert!(for_each_line(latin1, Decoding::Lossy, |_, _, _| Ok(())).is_ok());
+    }
+}
@@ -1,109 +1,513 @@
-pub fn split_input(string: &String) -> Vec<(String, String, String)> {
-    let mut words: Vec<String> = get_words(string);
-    let split_words: Vec<(String, String, String)> = words.iter_mut().map(|word| split_word(word)).collect();
+use std::ops::Range;
 
-    split_words
+/// A word found in the input, along with the byte range it occupies in the original text.
+#[derive(Clone, Debug, PartialEq)]
+#[readonly::make]
+pub struct Token {
+    pub word: String,
+    pub start: usize,
+    pub end: usize,
 }
 
-fn get_words(string: &String) -> Vec<String> {
-    string.split_whitespace().map(|s| s.to_string()).collect()
+/// Maps byte offsets in a text to one-based line and column numbers.
+#[readonly::make]
+pub struct LineIndex {
+    pub line_starts: Vec<usize>,
 }
 
-fn split_word(word: &mut String) -> (String, String, String) {
-    let mut starting_punctuations: String = String::new();
-    let mut middle_word: String = String::new();
-    let mut ending_punctuations: String = String::new();
+impl LineIndex {
+    pub fn new(string: &str) -> Self {
+        let mut line_starts: Vec<usize> = vec![0];
+        line_starts.extend(string.match_indices('\n').map(|(idx, _)| idx + 1));
 
-    for i in 0..word.len() {
-        if !word.chars().nth(i).unwrap().is_alphanumeric() {
-            starting_punctuations.push(word.chars().nth(i).unwrap());
-        } else {
-            middle_word = word.chars().skip(i).collect();
-            break;
+        Self { line_starts }
+    }
+
+    /// Returns the line and column of `offset`, with the column counted in characters.
+    pub fn position(&self, string: &str, offset: usize) -> (usize, usize) {
+        let line: usize = self.line_starts.partition_point(|&start| start <= offset);
+        let line_start: usize = self.line_starts[line - 1];
+
+        (line, string[line_start..offset].chars().count() + 1)
+    }
+}
+
+/// What a whitespace-separated chunk of text looks like. Everything but words is skipped by
+/// default, since checking it against the dictionary only produces noise.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TokenClass {
+    Word,
+    /// `https://example.com/foo`, `www.example.com`, `mailto:user@host.org`.
+    Url,
+    /// `user@host.org`.
+    Email,
+    /// `/usr/lib`, `./build.sh`, `src/main.rs`, `C:\Windows`.
+    Path,
+    /// Hexadecimal hashes and identifiers like `a3f9c21` or UUIDs.
+    Hash,
+    /// `42`, `3.14`, `1,000`, `50%`, `0x1F`, `2nd`, `100px`.
+    Number,
+    /// `v1.2.3`, `2.0.0-beta.1`.
+    Version,
+}
+
+/// The classes of tokens that are not checked unless asked for.
+pub const NON_WORDS: &[TokenClass] = &[
+    TokenClass::Url,
+    TokenClass::Email,
+    TokenClass::Path,
+    TokenClass::Hash,
+    TokenClass::Number,
+    TokenClass::Version,
+];
+
+impl TokenClass {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "url" => Some(TokenClass::Url),
+            "email" => Some(TokenClass::Email),
+            "path" => Some(TokenClass::Path),
+            "hash" => Some(TokenClass::Hash),
+            "number" => Some(TokenClass::Number),
+            "version" => Some(TokenClass::Version),
+            _ => None,
         }
     }
-    for i in (0..middle_word.len()).rev() {
-        if !middle_word.chars().nth(i).unwrap().is_alphanumeric() {
-            ending_punctuations.push(middle_word.chars().nth(i).unwrap());
-        } else {
-            middle_word = middle_word.chars().take(i + 1).collect();
-            break;
+}
+
+/// Classifies a whitespace-separated chunk of text, ignoring the brackets, quotes and sentence
+/// punctuation around it.
+pub fn classify(chunk: &str) -> TokenClass {
+    let chunk: &str = chunk
+        .trim_start_matches(['(', '[', '{', '<', '"', '\'', '`', '\u{201c}', '\u{2018}'])
+        .trim_end_matches([')', ']', '}', '>', '"', '\'', '`', '.', ',', ';', ':', '!', '?', '\u{201d}', '\u{2019}']);
+    let lower: String = chunk.to_lowercase();
+
+    let scheme: Option<usize> = lower.find("://");
+    if scheme.is_some_and(|end| end > 0 && lower[..end].chars().all(|c| c.is_ascii_alphanumeric() || c == '+'))
+        || lower.starts_with("www.")
+        || lower.starts_with("mailto:")
+    {
+        return TokenClass::Url;
+    }
+
+    if let Some((local, domain)) = chunk.split_once('@') {
+        if !local.is_empty() && !domain.contains('@') && domain.contains('.') && !domain.starts_with('.') {
+            return TokenClass::Email;
+        }
+    }
+
+    let is_version_core = |version: &str| {
+        let core: &str = version.split(['-', '+']).next().unwrap_or_default();
+        core.split('.').count() >= 2 && core.split('.').all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+    };
+    if is_version_core(lower.strip_prefix('v').unwrap_or(&lower)) && (lower.starts_with('v') || lower.split('.').count() >= 3) {
+        return TokenClass::Version;
+    }
+
+    // One slash between words is as likely to be prose, like `and/or`, so a path needs more to
+    // go on: an anchor at its start, a file extension, or several segments.
+    let segments: Vec<&str> = chunk.split(['/', '\\']).filter(|segment| !segment.is_empty()).collect();
+    let has_extension = |segment: &str| {
+        segment.rsplit_once('.').is_some_and(|(stem, extension)| {
+            !stem.is_empty() && (1..=5).contains(&extension.len()) && extension.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+    };
+    if chunk.starts_with('/') && chunk.len() > 1
+        || chunk.starts_with("~/")
+        || chunk.starts_with("./")
+        || chunk.starts_with("../")
+        || chunk.get(1..3).is_some_and(|prefix| prefix == ":\\" || prefix == ":/")
+        || chunk.contains(['/', '\\'])
+            && !chunk.contains("//")
+            && segments.len() >= 2
+            && (segments.len() >= 3 || segments.last().is_some_and(|segment| has_extension(segment)))
+    {
+        return TokenClass::Path;
+    }
+
+    let is_hex = |string: &str| string.chars().all(|c| c.is_ascii_hexdigit());
+    let is_uuid: bool = lower.split('-').map(str::len).eq([8, 4, 4, 4, 12]) && is_hex(&lower.replace('-', ""));
+    if is_uuid || lower.len() >= 7 && is_hex(&lower) && lower.contains(|c: char| c.is_ascii_digit()) && lower.contains(|c: char| c.is_ascii_alphabetic()) {
+        return TokenClass::Hash;
+    }
+
+    if chunk.trim_start_matches(['+', '-', '$', '€', '£', '#']).starts_with(|c: char| c.is_ascii_digit()) {
+        return TokenClass::Number;
+    }
+
+    TokenClass::Word
+}
+
+/// Splits `string` into words, leaving out chunks whose class is in `skipped`.
+pub fn tokenize(string: &str, skipped: &[TokenClass]) -> Vec<Token> {
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut chunk_start: Option<usize> = None;
+
+    for (idx, c) in string.char_indices().chain(std::iter::once((string.len(), ' '))) {
+        match (chunk_start, c.is_whitespace()) {
+            (None, false) => chunk_start = Some(idx),
+            (Some(start), true) => {
+                let chunk: &str = &string[start..idx];
+                let class: TokenClass = classify(chunk);
+                // Words joined by slashes, like `and/or`, are checked one by one.
+                let parts: Vec<&str> = match class {
+                    TokenClass::Word => chunk.split('/').collect(),
+                    _ => vec![chunk],
+                };
+                let mut part_start: usize = start;
+                for part in parts.into_iter().filter(|_| !skipped.contains(&class)) {
+                    let (starting_punctuations, middle_word, _) = split_word(part);
+                    if !middle_word.is_empty() {
+                        let word_start: usize = part_start + starting_punctuations.len();
+                        tokens.push(Token {
+                            start: word_start,
+                            end: word_start + middle_word.len(),
+                            word: middle_word,
+                        });
+                    }
+                    part_start += part.len() + 1;
+                }
+                chunk_start = None;
+            }
+            _ => {}
+        }
+    }
+
+    tokens
+}
+
+fn split_word(word: &str) -> (String, String, String) {
+    let middle_start: usize = word.find(|c: char| c.is_alphanumeric()).unwrap_or(word.len());
+    let middle_end: usize = word
+        .rfind(|c: char| c.is_alphanumeric())
+        .map(|idx| idx + word[idx..].chars().next().map_or(0, char::len_utf8))
+        .unwrap_or(middle_start);
+
+    (
+        word[..middle_start].to_string(),
+        word[middle_start..middle_end].to_string(),
+        word[middle_end..].to_string(),
+    )
+}
+
+/// Tokenizes only the given byte ranges of `string`, keeping offsets relative to the whole string.
+pub fn tokenize_regions(string: &str, regions: &[Range<usize>], skipped: &[TokenClass]) -> Vec<Token> {
+    regions
+        .iter()
+        .flat_map(|region| {
+            tokenize(&string[region.clone()], skipped).into_iter().map(|token| Token {
+                word: token.word,
+                start: region.start + token.start,
+                end: region.start + token.end,
+            })
+        })
+        .collect()
+}
+
+/// Replaces typographic apostrophes with `'`, so `don’t` and `don't` are the same word.
+pub fn normalize_apostrophes(word: &str) -> String {
+    word.replace(['\u{2019}', '\u{2018}', '\u{02bc}'], "'")
+}
+
+/// Splits a hyphenated compound like `state-of-the-art` into one token per component, keeping
+/// each component's span in the original text. Tokens without hyphens are returned whole.
+pub fn hyphen_parts(token: &Token) -> Vec<Token> {
+    if !token.word.contains(['-', '\u{2010}']) || token.end - token.start != token.word.len() {
+        return vec![token.clone()];
+    }
+
+    let mut parts: Vec<Token> = Vec::new();
+    let mut part_start: usize = 0;
+    for (idx, c) in token.word.char_indices().chain(std::iter::once((token.word.len(), '-'))) {
+        if c != '-' && c != '\u{2010}' {
+            continue;
+        }
+        let (_, part, _) = split_word(&token.word[part_start..idx]);
+        if !part.is_empty() {
+            let start: usize = part_start + token.word[part_start..idx].find(part.as_str()).unwrap_or(0);
+            parts.push(Token {
+                start: token.start + start,
+                end: token.start + start + part.len(),
+                word: part,
+            });
+        }
+        part_start = idx + c.len_utf8();
+    }
+
+    parts
+}
+
+/// Tokenizes text that was decoded from the original, such as HTML with its character
+/// references resolved. `offsets` holds the original offset of every byte of `decoded` plus one
+/// for its end, and token spans are mapped back through it.
+pub fn tokenize_decoded(decoded: &str, offsets: &[usize], skipped: &[TokenClass]) -> Vec<Token> {
+    tokenize(decoded, skipped)
+        .into_iter()
+        .map(|token| Token {
+            start: offsets[token.start],
+            end: offsets[token.end],
+            word: token.word,
+        })
+        .collect()
+}
+
+/// Abbreviations that are common in identifiers and accepted as subwords in identifier mode.
+pub const ABBREVIATIONS: &[&str] = &[
+    "addr", "alloc", "arg", "args", "async", "attr", "attrs", "auth", "bool", "btn", "buf", "cfg", "char", "cli",
+    "cmd", "config", "conn", "ctx", "cwd", "db", "decl", "def", "del", "desc", "dest", "dir", "dst", "elem", "env",
+    "err", "errno", "eval", "exe", "expr", "fd", "fmt", "fn", "func", "hash", "html", "http", "https", "idx", "impl",
+    "init", "int", "io", "iter", "js", "json", "len", "lhs", "lib", "max", "min", "msg", "mut", "num", "obj", "ok",
+    "opt", "opts", "param", "params", "pid", "pos", "ptr", "rect", "ref", "regex", "repo", "req", "res", "resp",
+    "rhs", "rx", "src", "stderr", "stdin", "stdout", "str", "struct", "sync", "sys", "tmp", "ts", "tx", "uid", "uri",
+    "url", "usize", "util", "utils", "val", "var", "vec", "xml", "yaml",
+];
+
+/// Splits an identifier into the byte ranges of its subwords. Underscores, hyphens and digits
+/// separate subwords, as do case changes: `recieveBuffer`, `MAX_LENGHT`, `HTTPServer` and
+/// `kebab-case` give `recieve Buffer`, `MAX LENGHT`, `HTTP Server` and `kebab case`.
+pub fn identifier_parts(word: &str) -> Vec<Range<usize>> {
+    let chars: Vec<(usize, char)> = word.char_indices().collect();
+    let mut parts: Vec<Range<usize>> = Vec::new();
+    let mut part_start: Option<usize> = None;
+
+    for (idx, &(offset, c)) in chars.iter().enumerate() {
+        let is_apostrophe: bool = matches!(c, '\'' | '\u{2019}')
+            && idx > 0
+            && chars[idx - 1].1.is_alphabetic()
+            && chars.get(idx + 1).is_some_and(|(_, next)| next.is_alphabetic());
+        if is_apostrophe {
+            continue;
+        }
+        if !c.is_alphabetic() {
+            if let Some(start) = part_start.take() {
+                parts.push(start..offset);
+            }
+            continue;
+        }
+
+        let previous: Option<char> = idx.checked_sub(1).map(|previous| chars[previous].1);
+        let next: Option<char> = chars.get(idx + 1).map(|(_, next)| *next);
+        let boundary: bool = match previous {
+            // `recieveBuffer`: a capital after a lowercase letter starts a new subword.
+            Some(previous) if previous.is_lowercase() => c.is_uppercase(),
+            // `HTTPServer`: the last capital of a run starts a new subword when a lowercase letter follows it.
+            Some(previous) if previous.is_uppercase() => c.is_uppercase() && next.is_some_and(char::is_lowercase),
+            _ => false,
+        };
+
+        match part_start {
+            Some(start) if boundary => {
+                parts.push(start..offset);
+                part_start = Some(offset);
+            }
+            None => part_start = Some(offset),
+            _ => {}
         }
     }
+    if let Some(start) = part_start {
+        parts.push(start..word.len());
+    }
 
-    (starting_punctuations, middle_word, ending_punctuations)
+    parts
 }
 
-pub fn join_input(split_words: Vec<(String, String, String)>) -> String {
-    let words: Vec<String> = split_words.iter().map(|parts| join_word(parts.clone())).collect();
-    words.join(" ")
+/// Replaces every compound token with one token per subword, keeping each subword's span in
+/// the original text. Single letters left over by splitting, like the `B` of `BTreeMap`, are dropped.
+pub fn split_identifiers(tokens: Vec<Token>) -> Vec<Token> {
+    tokens
+        .into_iter()
+        .flat_map(|token| {
+            let parts: Vec<Range<usize>> = identifier_parts(&token.word);
+            // A word decoded from its source no longer lines up with its span, so it is kept whole.
+            if token.end - token.start != token.word.len() || (parts.len() == 1 && parts[0] == (0..token.word.len())) {
+                return vec![token];
+            }
+
+            parts
+                .into_iter()
+                .filter(|part| token.word[part.clone()].chars().count() > 1)
+                .map(|part| Token {
+                    word: token.word[part.clone()].to_string(),
+                    start: token.start + part.start,
+                    end: token.start + part.end,
+                })
+                .collect()
+        })
+        .collect()
 }
 
-fn join_word(parts: (String, String, String)) -> String {
-    format!("{}{}{}", parts.0, parts.1, parts.2)
+/// Replaces each `(start, end, replacement)` byte range of `string`, leaving everything else untouched.
+/// The ranges must be sorted and must not overlap.
+pub fn splice(string: &str, replacements: &[(usize, usize, String)]) -> String {
+    let mut result: String = String::with_capacity(string.len());
+    let mut last_end: usize = 0;
+
+    for (start, end, replacement) in replacements {
+        result.push_str(&string[last_end..*start]);
+        result.push_str(replacement);
+        last_end = *end;
+    }
+    result.push_str(&string[last_end..]);
+
+    result
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{get_words, split_word};
+    use super::{
+        classify, hyphen_parts, identifier_parts, splice, split_identifiers, split_word, tokenize, tokenize_regions, LineIndex, Token,
+        TokenClass, NON_WORDS,
+    };
 
     #[test]
-    fn test_get_words() {
-        let string: String = "Hello, world!".to_string();
-        let words: Vec<String> = get_words(&string);
-        assert_eq!(words, vec!["Hello,", "world!"]);
+    fn test_tokenize() {
+        let string: &str = "Hello,  world!\n\t(indented)";
+        let tokens: Vec<Token> = tokenize(string, NON_WORDS);
+
+        assert_eq!(tokens.len(), 3);
+        assert_eq!((tokens[0].word.as_str(), tokens[0].start, tokens[0].end), ("Hello", 0, 5));
+        assert_eq!((tokens[1].word.as_str(), tokens[1].start, tokens[1].end), ("world", 8, 13));
+        assert_eq!((tokens[2].word.as_str(), tokens[2].start, tokens[2].end), ("indented", 17, 25));
+        assert_eq!(&string[tokens[2].start..tokens[2].end], "indented");
+    }
+
+    #[test]
+    fn test_classify() {
+        let cases: &[(&str, TokenClass)] = &[
+            ("https://example.com/foo,", TokenClass::Url),
+            ("(www.example.com)", TokenClass::Url),
+            ("user@host.org.", TokenClass::Email),
+            ("/usr/lib", TokenClass::Path),
+            ("src/main.rs", TokenClass::Path),
+            ("C:\\Windows", TokenClass::Path),
+            ("a3f9c21", TokenClass::Hash),
+            ("123e4567-e89b-12d3-a456-426614174000", TokenClass::Hash),
+            ("v1.2.3", TokenClass::Version),
+            ("2.0.0-beta.1", TokenClass::Version),
+            ("3.14", TokenClass::Number),
+            ("50%", TokenClass::Number),
+            ("2nd", TokenClass::Number),
+            ("deadbeef", TokenClass::Word),
+            ("Hello,", TokenClass::Word),
+            ("e.g.", TokenClass::Word),
+            ("and/or", TokenClass::Word),
+            ("recieve/send,", TokenClass::Word),
+            ("~/notes", TokenClass::Path),
+            ("docs/guide/intro", TokenClass::Path),
+            ("D:/data", TokenClass::Path),
+        ];
+
+        for (chunk, class) in cases {
+            assert_eq!(classify(chunk), *class, "{}", chunk);
+        }
+    }
+
+    #[test]
+    fn test_tokenize_skipped() {
+        let string: &str = "See https://example.com/foo or mail me@host.org about v1.2.3 in /usr/lib at a3f9c21, 42 times.";
+        let words = |skipped| tokenize(string, skipped).into_iter().map(|token| token.word).collect::<Vec<String>>();
+
+        assert_eq!(words(NON_WORDS), vec!["See", "or", "mail", "about", "in", "at", "times"]);
+        assert_eq!(words(&[TokenClass::Url]).len(), 12);
+        assert!(words(&[TokenClass::Url]).contains(&"me@host.org".to_string()));
+
+        let string: &str = "Use and/or (recieve/send).";
+        let tokens: Vec<Token> = tokenize(string, NON_WORDS);
+        assert_eq!(tokens.iter().map(|token| token.word.as_str()).collect::<Vec<&str>>(), vec!["Use", "and", "or", "recieve", "send"]);
+        assert_eq!(&string[tokens[3].start..tokens[3].end], "recieve");
+    }
+
+    #[test]
+    fn test_tokenize_regions() {
+        let string: &str = "skip `this` keep that";
+        let tokens: Vec<Token> = tokenize_regions(string, &[0..5, 11..21], NON_WORDS);
+
+        assert_eq!(tokens.iter().map(|token| token.word.as_str()).collect::<Vec<&str>>(), vec!["skip", "keep", "that"]);
+        assert_eq!((tokens[1].start, tokens[1].end), (12, 16));
+    }
+
+    #[test]
+    fn test_identifier_parts() {
+        let parts = |word: &'static str| identifier_parts(word).into_iter().map(|part| &word[part]).collect::<Vec<&str>>();
+
+        assert_eq!(parts("recieveBuffer"), vec!["recieve", "Buffer"]);
+        assert_eq!(parts("MAX_LENGHT"), vec!["MAX", "LENGHT"]);
+        assert_eq!(parts("HTTPServer2Config"), vec!["HTTP", "Server", "Config"]);
+        assert_eq!(parts("snake_case-and-kebab"), vec!["snake", "case", "and", "kebab"]);
+        assert_eq!(parts("__init__"), vec!["init"]);
+        assert_eq!(parts("naïveÉtat"), vec!["naïve", "État"]);
+        assert_eq!(parts("don't_panic"), vec!["don't", "panic"]);
+    }
+
+    #[test]
+    fn test_hyphen_parts() {
+        let string: &str = "a state-of-the-art, well--known idea";
+        let tokens: Vec<Token> = tokenize(string, NON_WORDS);
+        let parts = |token: &Token| hyphen_parts(token).into_iter().map(|part| (part.word, part.start)).collect::<Vec<_>>();
+
+        assert_eq!(parts(&tokens[0]), vec![("a".to_string(), 0)]);
+        assert_eq!(
+            parts(&tokens[1]),
+            vec![("state".to_string(), 2), ("of".to_string(), 8), ("the".to_string(), 11), ("art".to_string(), 15)]
+        );
+        assert_eq!(parts(&tokens[2]), vec![("well".to_string(), 20), ("known".to_string(), 26)]);
+    }
+
+    #[test]
+    fn test_split_identifiers() {
+        let string: &str = "call getUserNmae(BTreeMap) now";
+        let tokens: Vec<Token> = split_identifiers(tokenize(string, NON_WORDS));
+
+        assert_eq!(
+            tokens.iter().map(|token| &string[token.start..token.end]).collect::<Vec<&str>>(),
+            vec!["call", "get", "User", "Nmae", "Tree", "Map", "now"]
+        );
+        assert_eq!(tokens[3].word, "Nmae");
+    }
+
+    #[test]
+    fn test_line_index() {
+        let string: &str = "first line\nsécond line\n";
+        let line_index: LineIndex = LineIndex::new(string);
+
+        assert_eq!(line_index.position(string, 0), (1, 1));
+        assert_eq!(line_index.position(string, 6), (1, 7));
+        assert_eq!(line_index.position(string, 11), (2, 1));
+        assert_eq!(line_index.position(string, 19), (2, 8));
     }
 
     #[test]
     fn test_split_word() {
-        let mut word: String = "!!!Hello,".to_string();
-        let (starting_punctuations, middle_word, ending_punctuations): (String, String, String) = split_word(&mut word);
+        let word: String = "!!!Hello,".to_string();
+        let (starting_punctuations, middle_word, ending_punctuations): (String, String, String) = split_word(&word);
         assert_eq!(starting_punctuations, "!!!");
         assert_eq!(middle_word, "Hello");
         assert_eq!(ending_punctuations, ",");
 
-        word = "world!!!".to_string();
-        let (starting_punctuations, middle_word, ending_punctuations): (String, String, String) = split_word(&mut word);
+        let word: String = "world!!!".to_string();
+        let (starting_punctuations, middle_word, ending_punctuations): (String, String, String) = split_word(&word);
         assert_eq!(starting_punctuations, "");
         assert_eq!(middle_word, "world");
         assert_eq!(ending_punctuations, "!!!");
-    }
 
-    #[test]
-    fn test_split_input() {
-        let word: String = "Hello, how are you, my name is John.".to_string();
-        let split_words: Vec<(String, String, String)> = super::split_input(&word);
+        let (starting_punctuations, middle_word, ending_punctuations): (String, String, String) = split_word("«naïve»?!");
+        assert_eq!(starting_punctuations, "«");
+        assert_eq!(middle_word, "naïve");
+        assert_eq!(ending_punctuations, "»?!");
 
-        assert_eq!(split_words[0], ("".to_string(), "Hello".to_string(), ",".to_string()));
-        assert_eq!(split_words[1], ("".to_string(), "how".to_string(), "".to_string()));
-        assert_eq!(split_words[2], ("".to_string(), "are".to_string(), "".to_string()));
-        assert_eq!(split_words[3], ("".to_string(), "you".to_string(), ",".to_string()));
-        assert_eq!(split_words[4], ("".to_string(), "my".to_string(), "".to_string()));
-        assert_eq!(split_words[5], ("".to_string(), "name".to_string(), "".to_string()));
-        assert_eq!(split_words[6], ("".to_string(), "is".to_string(), "".to_string()));
-        assert_eq!(split_words[7], ("".to_string(), "John".to_string(), ".".to_string()));
+        let (starting_punctuations, middle_word, ending_punctuations): (String, String, String) = split_word("—");
+        assert_eq!(starting_punctuations, "—");
+        assert_eq!(middle_word, "");
+        assert_eq!(ending_punctuations, "");
     }
 
     #[test]
-    fn test_join_word() {
-        let parts: (String, String, String) = ("".to_string(), "Hello".to_string(), ",".to_string());
-        let word: String = super::join_word(parts);
-        assert_eq!(word, "Hello,");
-    }
+    fn test_splice() {
+        let string: &str = "Helo,\n\twrold!  \n";
+        let replacements: Vec<(usize, usize, String)> = vec![(0, 4, "Hello".to_string()), (7, 12, "world".to_string())];
 
-    #[test]
-    fn test_join_input() {
-        let split_words: Vec<(String, String, String)> = vec![
-            ("".to_string(), "Hello".to_string(), ",".to_string()),
-            ("".to_string(), "how".to_string(), "".to_string()),
-            ("".to_string(), "are".to_string(), "".to_string()),
-            ("".to_string(), "you".to_string(), ",".to_string()),
-            ("".to_string(), "my".to_string(), "".to_string()),
-            ("".to_string(), "name".to_string(), "".to_string()),
-            ("".to_string(), "is".to_string(), "".to_string()),
-            ("".to_string(), "John".to_string(), ".".to_string()),
-        ];
-        let input: String = super::join_input(split_words);
-        assert_eq!(input, "Hello, how are you, my name is John.");
+        assert_eq!(splice(string, &replacements), "Hello,\n\tworld!  \n");
+        assert_eq!(splice(string, &[]), string);
     }
 }
@@ -0,0 +1,106 @@
+use std::{
+    error::Error,
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::Path,
+};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// The controlling terminal, which prompts are answered on unless answers are given otherwise.
+const TERMINAL: &str = "/dev/tty";
+
+/// Whether there is a terminal to prompt on. Containers, CI runners and cron jobs usually have
+/// none, even when stdin or stdout are redirected to something else.
+pub fn has_terminal() -> bool {
+    OpenOptions::new().read(true).write(true).open(TERMINAL).is_ok()
+}
+
+/// Asks which suggestion replaces each misspelled word. An answer is the number of a suggestion,
+/// a word to use instead, or nothing or `0` to keep the word as it is.
+pub struct Prompter {
+    reader: Box<dyn BufRead>,
+    writer: Box<dyn Write>,
+    /// Repeats each answer after its prompt, since answers that are not typed are not shown.
+    echo: bool,
+}
+
+impl Prompter {
+    /// Prompts on the terminal, or reads the answers from `answers` one per line and prompts on
+    /// stderr, so that a script can drive the session.
+    pub fn open(answers: Option<&Path>) -> Result<Self> {
+        match answers {
+            Some(path) => {
+                let file: File = File::open(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+                Ok(Self {
+                    echo: true,
+                    ..Self::new(BufReader::new(file), io::stderr())
+                })
+            }
+            None => {
+                let terminal: File = OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .open(TERMINAL)
+                    .map_err(|err| format!("{}: {}; use --answers to answer from a file", TERMINAL, err))?;
+                Ok(Self::new(BufReader::new(terminal.try_clone()?), terminal))
+            }
+        }
+    }
+
+    pub fn new<R: BufRead + 'static, W: Write + 'static>(reader: R, writer: W) -> Self {
+        Self {
+            reader: Box::new(reader),
+            writer: Box::new(writer),
+            echo: false,
+        }
+    }
+
+    /// Shows `suggestions` for `word` and returns the replacement picked, if any. Running out of
+    /// answers keeps every remaining word.
+    pub fn choose(&mut self, word: &str, suggestions: &[(String, u8)]) -> Option<String> {
+        let mut question: String = format!("{} is incorrect.\n", word);
+        for (idx, (suggestion, _)) in suggestions.iter().enumerate() {
+            question.push_str(&format!("Suggestion: {} -> {}\n", idx + 1, suggestion));
+        }
+        question.push_str("Enter the suggestion number: ");
+        // Prompts are best effort; a closed stderr should not stop the answers from being read.
+        let _ = self.writer.write_all(question.as_bytes()).and_then(|_| self.writer.flush());
+
+        let mut answer: String = String::new();
+        let read: usize = self.reader.read_line(&mut answer).unwrap_or(0);
+        if self.echo {
+            let _ = writeln!(self.writer, "{}", answer.trim_end());
+        }
+        if read == 0 {
+            return None;
+        }
+
+        match answer.trim() {
+            "" => None,
+            answer => match answer.parse::<usize>() {
+                Ok(idx) => suggestions.get(idx.checked_sub(1)?).map(|(suggestion, _)| suggestion.to_string()),
+                Err(_) => Some(answer.to_string()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::Prompter;
+
+    #[test]
+    fn test_choose() {
+        let suggestions: Vec<(String, u8)> = vec![("hello".to_string(), 1), ("help".to_string(), 1)];
+        let mut prompter: Prompter = Prompter::new("2\n\n7\nhallo\n".as_bytes(), io::sink());
+
+        assert_eq!(prompter.choose("helo", &suggestions), Some("help".to_string()));
+        assert_eq!(prompter.choose("helo", &suggestions), None);
+        assert_eq!(prompter.choose("helo", &suggestions), None);
+        assert_eq!(prompter.choose("helo", &suggestions), Some("hallo".to_string()));
+        assert_eq!(prompter.choose("helo", &suggestions), None);
+    }
+}
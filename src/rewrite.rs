@@ -0,0 +1,172 @@
+use std::{
+    error::Error,
+    ffi::OsString,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use similar::TextDiff;
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// Where the corrected text ends up.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Destination {
+    Stdout,
+    /// Replace the input file, optionally keeping the original next to it as `<file>.bak`.
+    InPlace { backup: bool },
+    File(PathBuf),
+    /// Print a unified diff of the corrections instead of applying them.
+    Diff,
+}
+
+impl Destination {
+    /// Whether the corrected text can be written as it is produced, rather than needing the
+    /// whole document first.
+    pub fn is_streaming(&self) -> bool {
+        matches!(self, Destination::Stdout | Destination::File(_))
+    }
+
+    /// Opens a writer for a streaming destination, with `out` standing in for stdout.
+    pub fn stream<'a>(&self, out: &'a mut dyn Write) -> Result<Box<dyn Write + 'a>> {
+        match self {
+            Destination::Stdout => Ok(Box::new(out)),
+            Destination::File(path) => Ok(Box::new(BufWriter::new(File::create(path)?))),
+            Destination::InPlace { .. } | Destination::Diff => Err("Destination needs the whole document".into()),
+        }
+    }
+}
+
+/// Sends the corrected text of a whole document to `destination`, with `out` standing in for stdout.
+/// The document is rewritten at `path`, which piped input does not have, while `file_name` is
+/// only the name shown in diffs.
+pub fn emit(
+    destination: &Destination,
+    out: &mut dyn Write,
+    path: Option<&Path>,
+    file_name: &str,
+    original: &str,
+    corrected: &str,
+) -> Result<()> {
+    match destination {
+        Destination::Stdout => out.write_all(corrected.as_bytes())?,
+        Destination::InPlace { backup } => {
+            let path: &Path = path.ok_or("Piped input cannot be rewritten in place")?;
+            if original == corrected {
+                return Ok(());
+            }
+            if *backup {
+                fs::copy(path, backup_path(path))?;
+            }
+            write_atomic(path, corrected.as_bytes())?;
+        }
+        Destination::File(path) => fs::write(path, corrected)?,
+        Destination::Diff => out.write_all(unified_diff(file_name, original, corrected).as_bytes())?,
+    }
+
+    Ok(())
+}
+
+fn backup_path(path: &Path) -> PathBuf {
+    let mut backup: OsString = path.as_os_str().to_os_string();
+    backup.push(".bak");
+    PathBuf::from(backup)
+}
+
+/// Writes `contents` to a temporary file next to `path` and renames it over `path`, so readers
+/// never observe a partially written file. The permissions of a file already at `path` are
+/// carried over. The temporary file is named after the process, so concurrent writers do not
+/// write into each other's.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let mut temp_name: OsString = OsString::from(".");
+    temp_name.push(path.file_name().ok_or("Invalid file name")?);
+    temp_name.push(format!(".{}.spell_sweep.tmp", std::process::id()));
+    let temp_path: PathBuf = path.with_file_name(temp_name);
+
+    let result: Result<()> = (|| {
+        let mut file: File = File::create(&temp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        if let Ok(metadata) = fs::metadata(path) {
+            fs::set_permissions(&temp_path, metadata.permissions())?;
+        }
+        fs::rename(&temp_path, path)?;
+        Ok(())
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+pub fn unified_diff(file_name: &str, original: &str, corrected: &str) -> String {
+    TextDiff::from_lines(original, corrected)
+        .unified_diff()
+        .header(&format!("a/{}", file_name), &format!("b/{}", file_name))
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io, path::Path};
+
+    use super::{backup_path, emit, unified_diff, Destination};
+
+    #[test]
+    fn test_unified_diff() {
+        let diff: String = unified_diff("notes.txt", "Helo there\nhow are you\n", "Hello there\nhow are you\n");
+
+        assert!(diff.starts_with("--- a/notes.txt\n+++ b/notes.txt\n"));
+        assert!(diff.contains("-Helo there\n+Hello there\n"));
+        assert!(unified_diff("notes.txt", "same\n", "same\n").is_empty());
+    }
+
+    #[test]
+    fn test_in_place_with_backup() {
+        let path: &Path = Path::new("rewrite_test.txt");
+        fs::write(path, "Helo there\n").expect("Failed to write test file");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(path, fs::Permissions::from_mode(0o640)).expect("Failed to set permissions");
+        }
+
+        emit(&Destination::InPlace { backup: true }, &mut io::sink(), Some(path), "rewrite_test.txt", "Helo there\n", "Hello there\n")
+            .expect("Failed to rewrite file");
+
+        assert_eq!(fs::read_to_string(path).unwrap(), "Hello there\n");
+        assert_eq!(fs::read_to_string(backup_path(path)).unwrap(), "Helo there\n");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(path).unwrap().permissions().mode() & 0o777, 0o640);
+        }
+
+        fs::remove_file(path).expect("Failed to remove test file");
+        fs::remove_file(backup_path(path)).expect("Failed to remove backup file");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_in_place_non_utf8_name() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let path: &Path = Path::new(OsStr::from_bytes(b"rewrite_\xff_test.txt"));
+        fs::write(path, "Helo there\n").expect("Failed to write test file");
+
+        let file_name: String = path.display().to_string();
+        emit(&Destination::InPlace { backup: true }, &mut io::sink(), Some(path), &file_name, "Helo there\n", "Hello there\n")
+            .expect("Failed to rewrite file");
+
+        assert_eq!(fs::read_to_string(path).unwrap(), "Hello there\n");
+        assert_eq!(fs::read_to_string(backup_path(path)).unwrap(), "Helo there\n");
+        assert!(!Path::new(&file_name).exists());
+
+        fs::remove_file(path).expect("Failed to remove test file");
+        fs::remove_file(backup_path(path)).expect("Failed to remove backup file");
+    }
+}
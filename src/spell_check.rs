@@ -1,198 +1,780 @@
 use std::{
-    fs::{File, OpenOptions},
-    io::{self, BufRead, BufReader, Write},
-    path::Path,
+    collections::{HashMap, HashSet},
+    error::Error,
+    path::{Path, PathBuf},
+    process,
+    sync::OnceLock,
 };
 
-use crate::{bk_tree::BKTree, bloom_filter::BloomFilter, dictionary::Dictionary, processor};
+use crate::{
+    bk_tree::BKTree,
+    bloom_filter::BloomFilter,
+    cache,
+    cmd::EXIT_ERROR,
+    daemon::Client,
+    dictionary::Dictionary,
+    processor::{self, LineIndex, Token},
+    variants::{self, Locale},
+};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// Contractions that are not a word plus a suffix, so they cannot be checked through their stem.
+const CONTRACTIONS: &[&str] = &["can't", "won't", "shan't", "ain't", "y'all", "ma'am", "o'clock", "'tis", "'twas"];
+
+/// The verbs `n't` is added to, like `does` in `doesn't`. The irregular ones like `won't` are
+/// in `CONTRACTIONS`.
+const NEGATED_VERBS: &[&str] = &[
+    "is", "are", "was", "were", "do", "does", "did", "has", "have", "had", "could", "would", "should", "might", "must",
+    "need", "dare", "ought", "may",
+];
+
+/// Splits a contraction, possessive or elided name into its prefix, stem and suffix, like
+/// `children's`, `wouldn't` or `o'brien`.
+fn split_apostrophe(lower_word: &str) -> Option<(&str, &str, &str)> {
+    if let Some(stem) = lower_word.strip_suffix("n't").filter(|stem| NEGATED_VERBS.contains(stem)) {
+        return Some(("", stem, "n't"));
+    }
+    for suffix in ["'s", "'re", "'ve", "'ll", "'d", "'m"] {
+        if let Some(stem) = lower_word.strip_suffix(suffix).filter(|stem| !stem.is_empty() && !stem.contains('\'')) {
+            return Some(("", stem, suffix));
+        }
+    }
+    for prefix in ["o'", "d'", "l'"] {
+        if let Some(stem) = lower_word.strip_prefix(prefix).filter(|stem| !stem.is_empty() && !stem.contains('\'')) {
+            return Some((prefix, stem, ""));
+        }
+    }
+
+    None
+}
+
+/// How misspelled words are handled once they are found.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    /// Prompt on the terminal for every misspelled word.
+    Interactive,
+    /// Print every misspelling with its suggestions.
+    Report,
+    /// Print each unknown word once.
+    List,
+    /// Apply the top-ranked suggestion, optionally only when no other suggestion ties with it.
+    Fix { unambiguous: bool },
+}
+
+#[derive(Debug, PartialEq)]
+#[readonly::make]
+pub struct Misspelling {
+    pub word: String,
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+    pub suggestions: Vec<String>,
+}
+
+/// Where words are looked up.
+enum Lookup {
+    /// The BK-tree and bloom filter, loaded into this process.
+    Local { bk_tree: BKTree, bloom_filter: BloomFilter },
+    /// A `spell_sweep serve` daemon that holds them, and what to use if it stops answering.
+    Daemon { client: Client, fallback: Fallback },
+}
+
+/// The dictionary loaded into this process the first time the daemon fails to answer, so that a
+/// check goes on when the daemon is stopped in the middle of it.
+struct Fallback {
+    load: Box<dyn Fn() -> Result<SpellCheck> + Send + Sync>,
+    loaded: OnceLock<Box<SpellCheck>>,
+}
+
+impl Fallback {
+    /// The loaded dictionary, once the daemon has failed.
+    fn get(&self) -> Option<&SpellCheck> {
+        self.loaded.get().map(Box::as_ref)
+    }
+
+    /// Loads the dictionary after the daemon failed with `err`. There is nothing left to look
+    /// words up in when that fails too, so the run ends with an error.
+    fn load(&self, err: Box<dyn Error>) -> &SpellCheck {
+        self.loaded.get_or_init(|| {
+            eprintln!("Warning: The daemon failed: {}; loading the dictionary instead", err);
+            Box::new((self.load)().unwrap_or_else(|err| {
+                eprintln!("Error: {}", err);
+                process::exit(EXIT_ERROR);
+            }))
+        })
+    }
+}
 
 #[readonly::make]
 pub struct SpellCheck {
-    bk_tree: BKTree,
-    bloom_filter: BloomFilter,
+    lookup: Lookup,
+    /// Words accepted on top of the dictionary, lowercased.
+    extra_words: HashSet<String>,
+    /// Suggestions further than this edit distance from the word are not offered.
+    max_edit_distance: Option<u8>,
+    /// At most this many suggestions are offered.
+    suggestion_count: Option<usize>,
+    /// Words spelled for other locales than this one are misspelled, even if the dictionary has them.
+    locale: Option<Locale>,
 }
 
 impl SpellCheck {
+    /// Loads the BK-tree and bloom filter from their files, building them from the dictionary
+    /// and writing them first if they do not exist.
     pub fn new(
         bk_tree_path: &str,
         bloom_filter_path: &str,
-        dictionary_path: &str,
+        dictionary_paths: &[PathBuf],
         alphabet_length: u16,
-    ) -> Self {
+        fp_prob: f32,
+    ) -> Result<Self> {
         let bk_tree: BKTree;
         let bloom_filter: BloomFilter;
         let mut dictionary: Option<Dictionary> = None;
 
         if Path::new(bk_tree_path).exists() {
-            bk_tree = BKTree::from(File::open(bk_tree_path).expect("Failed to open BKTree file"));
+            bk_tree = BKTree::from_file(bk_tree_path).map_err(|err| format!("{}: {}", bk_tree_path, err))?;
         } else {
             if dictionary.is_none() {
-                dictionary = Some(Dictionary::from((
-                    File::open(dictionary_path).expect("Failed to open dictionary file"),
-                    alphabet_length,
-                )));
+                dictionary = Some(cache::load_dictionary(dictionary_paths, alphabet_length)?);
             }
-            bk_tree = BKTree::from(dictionary.as_ref().unwrap());
-            bk_tree
-                .to_file(bk_tree_path)
-                .expect("Failed to write BKTree to file");
+            bk_tree = BKTree::from(dictionary.as_ref().expect("The dictionary was just loaded"));
+            bk_tree.to_file(bk_tree_path).map_err(|err| format!("{}: {}", bk_tree_path, err))?;
         }
 
         if Path::new(bloom_filter_path).exists() {
-            bloom_filter = BloomFilter::from(
-                File::open(bloom_filter_path).expect("Failed to open BloomFilter file"),
-            );
+            bloom_filter = BloomFilter::from_file(bloom_filter_path).map_err(|err| format!("{}: {}", bloom_filter_path, err))?;
         } else {
             if dictionary.is_none() {
-                dictionary = Some(Dictionary::from((
-                    File::open(dictionary_path).expect("Failed to open dictionary file"),
-                    alphabet_length,
-                )));
+                dictionary = Some(cache::load_dictionary(dictionary_paths, alphabet_length)?);
             }
-            bloom_filter = BloomFilter::from(dictionary.as_ref().unwrap());
-            bloom_filter
-                .to_file(bloom_filter_path)
-                .expect("Failed to write BloomFilter to file");
+            bloom_filter = BloomFilter::from((dictionary.as_ref().expect("The dictionary was just loaded"), fp_prob));
+            bloom_filter.to_file(bloom_filter_path).map_err(|err| format!("{}: {}", bloom_filter_path, err))?;
         }
 
-        Self {
-            bk_tree,
-            bloom_filter,
+        Ok(Self::from(Lookup::Local { bk_tree, bloom_filter }))
+    }
+
+    /// Looks words up through the daemon `client` is connected to, or in what `fallback` loads if
+    /// the daemon fails.
+    pub fn with_daemon<F>(client: Client, fallback: F) -> Self
+    where
+        F: Fn() -> Result<SpellCheck> + Send + Sync + 'static,
+    {
+        Self::from(Lookup::Daemon {
+            client,
+            fallback: Fallback {
+                load: Box::new(fallback),
+                loaded: OnceLock::new(),
+            },
+        })
+    }
+
+    /// Accepts `words` on top of the dictionary.
+    pub fn with_words<I>(mut self, words: I) -> Self
+    where
+        I: IntoIterator<Item = String>,
+    {
+        self.add_words(words);
+        self
+    }
+
+    /// Accepts `words` on top of the dictionary from now on.
+    pub fn add_words<I>(&mut self, words: I)
+    where
+        I: IntoIterator<Item = String>,
+    {
+        self.extra_words
+            .extend(words.into_iter().map(|word| processor::normalize_apostrophes(&word.to_lowercase())));
+    }
+
+    /// Limits how far suggestions may be from the misspelled word and how many are offered.
+    pub fn with_limits(mut self, max_edit_distance: Option<u8>, suggestion_count: Option<usize>) -> Self {
+        self.max_edit_distance = max_edit_distance;
+        self.suggestion_count = suggestion_count;
+        self
+    }
+
+    /// Holds words to the spelling of `locale`, so `colour` is misspelled under en-US with `color`
+    /// as the suggestion.
+    pub fn with_locale(mut self, locale: Option<Locale>) -> Self {
+        self.locale = locale;
+        self
+    }
+
+    /// The spelling of `lower_word` for the locale words are held to, if it is spelled for
+    /// another one.
+    fn locale_spelling(&self, lower_word: &str) -> Option<&'static str> {
+        variants::spelling_for(lower_word, self.locale?)
+    }
+
+    fn contains(&self, lower_word: &str) -> bool {
+        self.contains_known(lower_word, &HashMap::new())
+    }
+
+    /// Like [`SpellCheck::contains`], taking the answer from `known` when it has one.
+    fn contains_known(&self, lower_word: &str, known: &HashMap<String, bool>) -> bool {
+        if self.extra_words.contains(lower_word) {
+            return true;
+        }
+        if let Some(answer) = known.get(lower_word) {
+            return *answer;
+        }
+
+        match &self.lookup {
+            Lookup::Local { bk_tree, bloom_filter } => {
+                bloom_filter.lookup(lower_word)
+                    && bk_tree
+                        .does_contain(lower_word)
+                        .expect("Failed to look up word in BKTree")
+            }
+            Lookup::Daemon { fallback, .. } if fallback.get().is_some() => {
+                fallback.get().is_some_and(|local| local.contains(lower_word))
+            }
+            Lookup::Daemon { client, fallback } => client
+                .is_correct(lower_word)
+                .unwrap_or_else(|err| fallback.load(err).contains(lower_word)),
         }
     }
 
-    fn handle_suggestions(word: &str, suggestions: Vec<&str>) -> String {
-        println!("{} is incorrect.", word);
-        for (idx, suggestion) in suggestions.iter().enumerate() {
-            println!("Suggestion: {} -> {}", idx + 1, suggestion);
+    /// Whether `word` is spelled correctly. Contractions and possessives are correct when the
+    /// dictionary has them, when they are common irregular ones like `won't`, or when the word
+    /// they are built on is correct.
+    pub fn is_correct(&self, word: &str) -> bool {
+        self.is_correct_known(word, &HashMap::new())
+    }
+
+    /// Like [`SpellCheck::is_correct`], taking the answers of the dictionary from `known` when it
+    /// has them.
+    fn is_correct_known(&self, word: &str, known: &HashMap<String, bool>) -> bool {
+        let lower_word: String = processor::normalize_apostrophes(&word.to_lowercase());
+        let stem: Option<&str> = split_apostrophe(&lower_word).map(|(_, stem, _)| stem);
+        if self.locale_spelling(&lower_word).or_else(|| self.locale_spelling(stem?)).is_some() {
+            return false;
+        }
+        if self.contains_known(&lower_word, known) {
+            return true;
         }
 
-        let idx = take_input();
+        CONTRACTIONS.contains(&lower_word.as_str())
+            || split_apostrophe(&lower_word).is_some_and(|(_, stem, _)| self.contains_known(stem, known))
+    }
 
-        return suggestions[(idx - 1) as usize].to_string();
+    /// Asks the daemon about every word checking `tokens` may look up, in one request rather than
+    /// one per word. Words are looked up in a local dictionary as they come instead.
+    fn look_up_all(&self, tokens: &[Token]) -> HashMap<String, bool> {
+        let Lookup::Daemon { client, fallback } = &self.lookup else {
+            return HashMap::new();
+        };
+        if fallback.get().is_some() {
+            return HashMap::new();
+        }
+
+        let mut words: Vec<String> = Vec::new();
+        let mut seen: HashSet<String> = HashSet::new();
+        for token in tokens {
+            let mut parts: Vec<Token> = processor::hyphen_parts(token);
+            if parts.len() > 1 {
+                parts.push(token.clone());
+            }
+            for part in parts {
+                let lower_word: String = processor::normalize_apostrophes(&part.word.to_lowercase());
+                let stem: Option<String> = split_apostrophe(&lower_word).map(|(_, stem, _)| stem.to_string());
+                for word in std::iter::once(lower_word).chain(stem) {
+                    if !self.extra_words.contains(&word) && seen.insert(word.clone()) {
+                        words.push(word);
+                    }
+                }
+            }
+        }
+        if words.is_empty() {
+            return HashMap::new();
+        }
+
+        match client.check(&words) {
+            Ok(answers) => words.into_iter().zip(answers).collect(),
+            Err(err) => {
+                fallback.load(err);
+                HashMap::new()
+            }
+        }
     }
 
-    fn insert_suggestion(
-        bk_tree: &BKTree,
-        word: &str,
-        lower_word: &str,
-        joinable_vec: &mut Vec<(String, String, String)>,
-        data: (String, String),
-    ) {
-        let mut tol_value = 1;
-        let mut suggestions;
-        loop {
-            suggestions = bk_tree.get_similar_words(&lower_word, tol_value).unwrap();
-            if suggestions.len() > 0 {
+    /// The dictionary word that `word` is accepted through when it is not in the dictionary
+    /// itself, like `child` for `child's`.
+    pub fn root(&self, word: &str) -> Option<String> {
+        let lower_word: String = processor::normalize_apostrophes(&word.to_lowercase());
+        if self.contains(&lower_word) || CONTRACTIONS.contains(&lower_word.as_str()) {
+            return None;
+        }
+
+        split_apostrophe(&lower_word)
+            .map(|(_, stem, _)| stem.to_string())
+            .filter(|stem| self.contains(stem))
+    }
+
+    pub fn suggest(&self, word: &str) -> Vec<(String, u8)> {
+        let lower_word: String = processor::normalize_apostrophes(&word.to_lowercase());
+
+        // `childern's` gets the suggestions for `childern`, with the `'s` put back on.
+        if let Some((prefix, stem, suffix)) = split_apostrophe(&lower_word) {
+            return self
+                .suggest(stem)
+                .into_iter()
+                .map(|(suggestion, distance)| (format!("{}{}{}", prefix, suggestion, suffix), distance))
+                .collect();
+        }
+        // A word spelled for another locale has one spelling it should have instead.
+        if let Some(spelling) = self.locale_spelling(&lower_word) {
+            return vec![(spelling.to_string(), 1)];
+        }
+
+        let bk_tree: &BKTree = match &self.lookup {
+            Lookup::Local { bk_tree, .. } => bk_tree,
+            Lookup::Daemon { client, fallback } => {
+                return match fallback.get() {
+                    Some(local) => local.suggest(&lower_word),
+                    None => client
+                        .suggest(&lower_word, self.max_edit_distance, self.suggestion_count)
+                        .unwrap_or_else(|err| fallback.load(err).suggest(&lower_word)),
+                };
+            }
+        };
+
+        // Corrections from the dictionary's replacement table come first, since its authors
+        // expect them to be what was meant.
+        let mut suggestions: Vec<(String, u8)> = self.replacement_suggestions(bk_tree, &lower_word);
+        let max_tolerance: u8 = bk_tree.max_word_length.min(u8::MAX as u16) as u8;
+        for tol_value in 1..=self.max_edit_distance.map_or(max_tolerance, |distance| distance.min(max_tolerance)) {
+            let similar_words: Vec<(&str, u8)> = bk_tree
+                .get_ranked_similar_words(&lower_word, tol_value)
+                .expect("Failed to get similar words from BKTree");
+            if !similar_words.is_empty() {
+                for (suggestion, distance) in similar_words {
+                    if !suggestions.iter().any(|(replacement, _)| replacement == suggestion) {
+                        suggestions.push((suggestion.to_string(), distance));
+                    }
+                }
                 break;
             }
-            tol_value += 1;
-        }
-        joinable_vec.push((
-            data.0,
-            convert_case(
-                SpellCheck::handle_suggestions(&lower_word, suggestions).as_str(),
-                &word,
-            ),
-            data.1,
-        ));
-    }
-
-    pub fn run(&self, cmd_data: String) {
-        let mut joinable_vec = Vec::<(String, String, String)>::new();
-
-        for (start_punc, word, end_punc) in processor::split_input(&cmd_data) {
-            let lower_word = word.to_lowercase();
-            if !self.bloom_filter.lookup(&lower_word) {
-                SpellCheck::insert_suggestion(
-                    &self.bk_tree,
-                    &word,
-                    &lower_word,
-                    &mut joinable_vec,
-                    (start_punc, end_punc),
-                );
-            } else {
-                if self.bk_tree.does_contain(&lower_word).unwrap() {
-                    joinable_vec.push((start_punc, convert_case(&lower_word, &word), end_punc));
-                } else {
-                    SpellCheck::insert_suggestion(
-                        &self.bk_tree,
-                        &word,
-                        &lower_word,
-                        &mut joinable_vec,
-                        (start_punc, end_punc),
-                    );
+        }
+        let suggestions: Vec<(String, u8)> = limit_suggestions(suggestions, self.max_edit_distance, self.suggestion_count);
+
+        suggestions
+    }
+
+    /// The correct words that the replacement table of `bk_tree` turns `lower_word` into, with
+    /// their edit distance from it. A replacement that splits the word is correct when every
+    /// part of it is.
+    fn replacement_suggestions(&self, bk_tree: &BKTree, lower_word: &str) -> Vec<(String, u8)> {
+        let mut suggestions: Vec<(String, u8)> = Vec::new();
+
+        for replacement in bk_tree.replacements.iter() {
+            let from: &str = replacement.from.trim_start_matches('^').trim_end_matches('$');
+            if from.is_empty() {
+                continue;
+            }
+            let to: String = replacement.to.replace('_', " ");
+            for (start, _) in lower_word.match_indices(from) {
+                let end: usize = start + from.len();
+                if (replacement.from.starts_with('^') && start != 0) || (replacement.from.ends_with('$') && end != lower_word.len()) {
+                    continue;
+                }
+                let candidate: String = format!("{}{}{}", &lower_word[..start], to, &lower_word[end..]);
+                if candidate.split(' ').all(|part| !part.is_empty() && self.contains(part))
+                    && !suggestions.iter().any(|(suggestion, _)| *suggestion == candidate)
+                {
+                    let distance: u8 = bk_tree
+                        .get_damerau_levenshtein_distance(lower_word, &candidate)
+                        .expect("Failed to compute the edit distance");
+                    suggestions.push((candidate, distance));
                 }
             }
         }
 
-        println!("{}", processor::join_input(joinable_vec));
+        suggestions
     }
-}
 
-fn convert_case(sugg: &str, orig: &str) -> String {
-    let mut result = String::new();
+    /// Returns the misspelled tokens. A hyphenated compound that is not in the dictionary is
+    /// correct when all of its components are, and otherwise only the misspelled components are
+    /// returned.
+    pub fn misspelled(&self, tokens: Vec<Token>) -> Vec<Token> {
+        let known: HashMap<String, bool> = self.look_up_all(&tokens);
+
+        tokens
+            .into_iter()
+            .filter(|token| !self.is_correct_known(&token.word, &known))
+            .flat_map(|token| processor::hyphen_parts(&token))
+            .filter(|token| !self.is_correct_known(&token.word, &known))
+            .collect()
+    }
+
+    /// Finds the misspelled tokens in a chunk of a larger text that starts `line_offset` lines and
+    /// `byte_offset` bytes into it, so positions are reported relative to the whole text.
+    pub fn misspelled_tokens(&self, cmd_data: &str, tokens: Vec<Token>, line_offset: usize, byte_offset: usize) -> Vec<Misspelling> {
+        let line_index: LineIndex = LineIndex::new(cmd_data);
+
+        self.misspelled(tokens)
+            .into_iter()
+            .map(|token| {
+                let suggestions: Vec<String> = self
+                    .suggest(&token.word)
+                    .into_iter()
+                    .map(|(suggestion, _)| convert_case(&suggestion, &token.word))
+                    .collect();
+                let (line, column) = line_index.position(cmd_data, token.start);
+                Misspelling {
+                    word: token.word.clone(),
+                    start: byte_offset + token.start,
+                    end: byte_offset + token.end,
+                    line: line_offset + line,
+                    column,
+                    suggestions,
+                }
+            })
+            .collect()
+    }
 
-    for i in 0..sugg.len() {
-        if orig.chars().nth(i).is_none() {
-            result.push(sugg.chars().nth(i).unwrap());
-        } else if !sugg.chars().nth(i).unwrap().is_alphanumeric() {
-            result.push(sugg.chars().nth(i).unwrap());
-        } else if orig.chars().nth(i).unwrap().is_lowercase() {
-            result.push(sugg.chars().nth(i).unwrap().to_lowercase().next().unwrap());
-        } else if orig.chars().nth(i).unwrap().is_uppercase() {
-            result.push(sugg.chars().nth(i).unwrap().to_uppercase().next().unwrap());
+    pub fn correct_tokens<F>(&self, cmd_data: &str, tokens: Vec<Token>, choose: F) -> String
+    where
+        F: FnMut(&str, &[(String, u8)]) -> Option<String>,
+    {
+        processor::splice(cmd_data, &self.corrections(tokens, choose))
+    }
+
+    /// Returns a `(start, end, replacement)` for every misspelled token that `choose` picks a
+    /// suggestion for, with the suggestion in the token's casing.
+    pub fn corrections<F>(&self, tokens: Vec<Token>, mut choose: F) -> Vec<(usize, usize, String)>
+    where
+        F: FnMut(&str, &[(String, u8)]) -> Option<String>,
+    {
+        let mut replacements: Vec<(usize, usize, String)> = Vec::new();
+
+        for token in self.misspelled(tokens) {
+            let suggestions: Vec<(String, u8)> = self.suggest(&token.word);
+            if let Some(suggestion) = choose(&token.word.to_lowercase(), &suggestions) {
+                replacements.push((token.start, token.end, convert_case(&suggestion, &token.word)));
+            }
         }
+
+        replacements
     }
 
-    result
+    pub fn pick_top_suggestion(suggestions: &[(String, u8)], unambiguous: bool) -> Option<String> {
+        let (top, distance) = suggestions.first()?;
+        let ties: usize = suggestions.iter().filter(|(_, d)| d == distance).count();
+
+        if unambiguous && ties > 1 {
+            return None;
+        }
+        Some(top.to_string())
+    }
+}
+
+impl From<Lookup> for SpellCheck {
+    fn from(lookup: Lookup) -> Self {
+        Self {
+            lookup,
+            extra_words: HashSet::new(),
+            max_edit_distance: None,
+            suggestion_count: None,
+            locale: None,
+        }
+    }
+}
+
+impl From<&Dictionary> for SpellCheck {
+    fn from(value: &Dictionary) -> Self {
+        Self::from(Lookup::Local {
+            bk_tree: BKTree::from(value),
+            bloom_filter: BloomFilter::from(value),
+        })
+    }
+}
+
+/// Drops the suggestions further than `max_edit_distance` from the word, replacements included,
+/// and keeps the first `suggestion_count`. Suggestions found in this process and by the daemon
+/// both go through here, so a run offers the same ones either way.
+pub fn limit_suggestions(
+    suggestions: Vec<(String, u8)>,
+    max_edit_distance: Option<u8>,
+    suggestion_count: Option<usize>,
+) -> Vec<(String, u8)> {
+    suggestions
+        .into_iter()
+        .filter(|(_, distance)| max_edit_distance.is_none_or(|max_edit_distance| *distance <= max_edit_distance))
+        .take(suggestion_count.unwrap_or(usize::MAX))
+        .collect()
 }
 
-fn take_input() -> u32 {
-    print!("Enter the suggestion number: ");
-    io::stdout().flush().unwrap();
+/// Finds the words of `tokens` spelled for another locale than most of the others, like `color`
+/// in a text that otherwise writes `colour`, along with the prevailing spelling of each.
+fn inconsistent(tokens: Vec<Token>) -> Vec<(Token, String)> {
+    let parts: Vec<Token> = tokens.iter().flat_map(processor::hyphen_parts).collect();
+    let lower_words: Vec<String> = parts
+        .iter()
+        .map(|token| processor::normalize_apostrophes(&token.word.to_lowercase()))
+        .collect();
+    fn split(lower_word: &str) -> (&str, &str, &str) {
+        split_apostrophe(lower_word).unwrap_or(("", lower_word, ""))
+    }
+    let Some(locale) = variants::prevailing_locale(lower_words.iter().map(|lower_word| split(lower_word).1)) else {
+        return Vec::new();
+    };
 
-    let fd = OpenOptions::new()
-        .read(true)
-        .write(true)
-        .open("/dev/tty")
-        .unwrap();
+    parts
+        .into_iter()
+        .zip(lower_words.iter())
+        .filter_map(|(token, lower_word)| {
+            let (prefix, stem, suffix) = split(lower_word);
+            let spelling: &str = variants::spelling_for(stem, locale)?;
+            Some((token, format!("{}{}{}", prefix, spelling, suffix)))
+        })
+        .collect()
+}
 
-    let mut reader = BufReader::new(fd);
+/// Reports the words of `tokens` that break with the spelling most of a text uses, in a chunk
+/// of it like [`SpellCheck::misspelled_tokens`] does for misspelled words.
+pub fn inconsistencies(text: &str, tokens: Vec<Token>, line_offset: usize, byte_offset: usize) -> Vec<Misspelling> {
+    let line_index: LineIndex = LineIndex::new(text);
 
-    let mut input = String::new();
-    reader.read_line(&mut input).unwrap();
+    inconsistent(tokens)
+        .into_iter()
+        .map(|(token, spelling)| {
+            let (line, column) = line_index.position(text, token.start);
+            Misspelling {
+                suggestions: vec![convert_case(&spelling, &token.word)],
+                word: token.word.clone(),
+                start: byte_offset + token.start,
+                end: byte_offset + token.end,
+                line: line_offset + line,
+                column,
+            }
+        })
+        .collect()
+}
+
+/// Returns a `(start, end, replacement)` for every word of `tokens` that breaks with the spelling
+/// most of a text uses, when `choose` takes the prevailing spelling.
+pub fn consistency_corrections<F>(tokens: Vec<Token>, mut choose: F) -> Vec<(usize, usize, String)>
+where
+    F: FnMut(&str, &[(String, u8)]) -> Option<String>,
+{
+    inconsistent(tokens)
+        .into_iter()
+        .filter_map(|(token, spelling)| {
+            let suggestion: String = choose(&token.word.to_lowercase(), &[(spelling, 1)])?;
+            Some((token.start, token.end, convert_case(&suggestion, &token.word)))
+        })
+        .collect()
+}
+
+/// Applies the casing and apostrophe style of `orig` to `sugg`. An all-caps word like `LENGHT`
+/// stays all caps even when the suggestion is longer than the original.
+pub fn convert_case(sugg: &str, orig: &str) -> String {
+    if orig.contains('\u{2019}') {
+        return convert_case(&sugg.replace('\'', "\u{2019}"), &orig.replace('\u{2019}', "'"));
+    }
 
-    input.trim().parse::<u32>().unwrap()
+    let mut letters = orig.chars().filter(|c| c.is_alphabetic());
+    if letters.clone().count() > 1 && letters.all(char::is_uppercase) {
+        return sugg.to_uppercase();
+    }
+
+    let mut result = String::new();
+    let mut orig_chars = orig.chars();
+
+    for sugg_char in sugg.chars() {
+        match orig_chars.next() {
+            Some(orig_char) if sugg_char.is_alphanumeric() && orig_char.is_uppercase() => {
+                result.extend(sugg_char.to_uppercase());
+            }
+            Some(orig_char) if sugg_char.is_alphanumeric() && orig_char.is_lowercase() => {
+                result.extend(sugg_char.to_lowercase());
+            }
+            _ => result.push(sugg_char),
+        }
+    }
+
+    result
 }
 
 #[cfg(test)]
 mod tests {
-    use super::SpellCheck;
+    use std::{env, fs, os::unix::net::UnixListener, path::PathBuf};
+
+    use super::{consistency_corrections, inconsistencies, Lookup, Misspelling, SpellCheck};
+    use crate::{daemon::Client, dictionary::Dictionary, processor, variants::Locale};
+
+    fn small_spell_check() -> SpellCheck {
+        let words: Vec<String> = ["hello", "help", "hell", "world", "how", "are", "you", "do", "child", "children", "state", "of", "the", "art"]
+            .iter()
+            .map(|word| word.to_string())
+            .collect();
+        SpellCheck::from(&Dictionary::from((words, 255)))
+    }
 
     #[test]
     fn test_new() {
         let bk_tree_path: &str = "bk_tree_test.bin";
         let bloom_filter_path: &str = "bloom_filter_test.bin";
-        let dictionary_path: &str = "dictionary.txt";
+        let dictionary_path: PathBuf = PathBuf::from("dictionary.txt");
         let alphabet_length: u16 = 255;
 
         let spell_check: SpellCheck = SpellCheck::new(
             bk_tree_path,
             bloom_filter_path,
-            dictionary_path,
+            std::slice::from_ref(&dictionary_path),
             alphabet_length,
-        );
+            0.01,
+        )
+        .expect("Failed to load the dictionary");
 
-        assert_ne!(spell_check.bk_tree.tree.len(), 0);
-        assert_eq!(spell_check.bk_tree.alphabet_length, alphabet_length);
+        let Lookup::Local { bk_tree, bloom_filter } = &spell_check.lookup else {
+            panic!("A spell check loaded from files should look words up locally");
+        };
+        assert_ne!(bk_tree.tree.len(), 0);
+        assert_eq!(bk_tree.alphabet_length, alphabet_length);
 
         let words_absent = ["clesr", "erroe", "hel;", "rivee", "jokeq", "fathep"];
         for word in words_absent {
-            assert_eq!(spell_check.bloom_filter.lookup(word), false);
+            assert!(!bloom_filter.lookup(word));
         }
 
         std::fs::remove_file(bk_tree_path).expect("Failed to remove BKTree file");
         std::fs::remove_file(bloom_filter_path).expect("Failed to remove BloomFilter file");
     }
+
+    #[test]
+    fn test_misspellings() {
+        let spell_check: SpellCheck = small_spell_check();
+        let text: &str = "Helo, how are yuo?";
+        let misspellings: Vec<Misspelling> = spell_check.misspelled_tokens(text, processor::tokenize(text, processor::NON_WORDS), 0, 0);
+
+        assert_eq!(misspellings.len(), 2);
+        assert_eq!(misspellings[0].word, "Helo");
+        assert_eq!((misspellings[0].start, misspellings[0].end), (0, 4));
+        assert_eq!(misspellings[0].suggestions, vec!["Hell", "Help", "Hello"]);
+        assert_eq!(misspellings[1].word, "yuo");
+        assert_eq!((misspellings[1].line, misspellings[1].column), (1, 15));
+        assert_eq!(misspellings[1].suggestions, vec!["you"]);
+    }
+
+    #[test]
+    fn test_apostrophes_and_compounds() {
+        let spell_check: SpellCheck = small_spell_check();
+
+        for word in ["don't", "don’t", "children's", "Child's", "you'll", "won't"] {
+            assert!(spell_check.is_correct(word), "{}", word);
+        }
+        assert!(!spell_check.is_correct("wasn't"));
+        // Only verbs are negated, however correct the word before `n't` is.
+        assert!(!spell_check.is_correct("hellon't"));
+        assert!(!spell_check.is_correct("childn't"));
+
+        let text: &str = "The state-of-teh-art childern's help";
+        let misspellings: Vec<Misspelling> =
+            spell_check.misspelled_tokens(text, processor::tokenize(text, processor::NON_WORDS), 0, 0);
+        assert_eq!(misspellings.len(), 2);
+        assert_eq!((misspellings[0].word.as_str(), misspellings[0].start), ("teh", 13));
+        assert_eq!(misspellings[0].suggestions[0], "the");
+        assert_eq!(misspellings[1].word, "childern's");
+        assert_eq!(misspellings[1].suggestions, vec!["children's"]);
+    }
+
+    #[test]
+    fn test_words_and_limits() {
+        let spell_check: SpellCheck = small_spell_check()
+            .with_words(["Rkyv".to_string()])
+            .with_limits(Some(1), Some(2));
+
+        assert!(spell_check.is_correct("rkyv"));
+        assert_eq!(spell_check.suggest("helo"), vec![("hell".to_string(), 1), ("help".to_string(), 1)]);
+        assert!(spell_check.suggest("hxxxo").is_empty());
+    }
+
+    #[test]
+    fn test_locale_and_consistency() {
+        let words: Vec<String> = ["the", "color", "colour", "of", "centre", "center"].iter().map(|word| word.to_string()).collect();
+        let spell_check: SpellCheck = SpellCheck::from(&Dictionary::from((words, 255))).with_locale(Some(Locale::Us));
+
+        assert!(spell_check.is_correct("Color"));
+        assert!(!spell_check.is_correct("Colour"));
+        assert!(!spell_check.is_correct("colour's"));
+        assert_eq!(spell_check.suggest("colour"), vec![("color".to_string(), 1)]);
+        assert_eq!(spell_check.suggest("colour's"), vec![("color's".to_string(), 1)]);
+        assert_eq!(spell_check.corrections(processor::tokenize("Centre", &[]), |_, suggestions| Some(suggestions[0].0.clone())), vec![(0, 6, "Center".to_string())]);
+
+        let text: &str = "The colour of the centre,\nthe Color of the center-line";
+        let words: Vec<(String, usize, usize, Vec<String>)> = inconsistencies(text, processor::tokenize(text, &[]), 2, 10)
+            .into_iter()
+            .map(|misspelling| (misspelling.word.clone(), misspelling.line, misspelling.column, misspelling.suggestions.clone()))
+            .collect();
+        assert_eq!(
+            words,
+            vec![("Color".to_string(), 4, 5, vec!["Colour".to_string()]), ("center".to_string(), 4, 18, vec!["centre".to_string()])]
+        );
+        let choose = |_: &str, suggestions: &[(String, u8)]| Some(suggestions[0].0.clone());
+        assert_eq!(consistency_corrections(processor::tokenize("colour color", &[]), choose), vec![(7, 12, "colour".to_string())]);
+        assert!(inconsistencies("color and center", processor::tokenize("color and center", &[]), 0, 0).is_empty());
+    }
+
+    #[test]
+    fn test_dictionary_rules() {
+        let words: Vec<String> = ["phone", "photo", "a", "lot", "damn", "dame"].iter().map(|word| word.to_string()).collect();
+        let dictionary: Dictionary = Dictionary::from((words, 255)).with_rules(
+            vec!["damn".to_string()],
+            vec![("f".to_string(), "ph".to_string()), ("^alot$".to_string(), "a_lot".to_string())],
+        );
+        let spell_check: SpellCheck = SpellCheck::from(&dictionary).with_limits(Some(2), None);
+
+        assert!(spell_check.is_correct("damn"));
+        assert_eq!(spell_check.suggest("damm"), vec![("dame".to_string(), 1)]);
+        assert_eq!(spell_check.suggest("fone")[0], ("phone".to_string(), 2));
+        assert_eq!(spell_check.suggest("alot")[0], ("a lot".to_string(), 1));
+        assert!(!spell_check.with_limits(Some(1), None).suggest("fone").iter().any(|(suggestion, _)| suggestion == "phone"));
+    }
+
+    #[test]
+    fn test_fix() {
+        let spell_check: SpellCheck = small_spell_check();
+
+        let fix = |text: &str, tokens: Vec<processor::Token>, unambiguous: bool| {
+            spell_check.correct_tokens(text, tokens, |_, suggestions| {
+                SpellCheck::pick_top_suggestion(suggestions, unambiguous)
+            })
+        };
+
+        let text: &str = "Helo, how are yuo?";
+        assert_eq!(fix(text, processor::tokenize(text, processor::NON_WORDS), false), "Hell, how are you?");
+        assert_eq!(fix(text, processor::tokenize(text, processor::NON_WORDS), true), "Helo, how are you?");
+
+        let text: &str = "  Helo,\n\thow  are\r\n  YUO?\n";
+        assert_eq!(fix(text, processor::tokenize(text, processor::NON_WORDS), false), "  Hell,\n\thow  are\r\n  YOU?\n");
+
+        let text: &str = "Yuo’ll help";
+        assert_eq!(fix(text, processor::tokenize(text, processor::NON_WORDS), false), "You’ll help");
+
+        let text: &str = "helloWrold(HELO_YUO, how-ar)";
+        let tokens = processor::split_identifiers(processor::tokenize(text, processor::NON_WORDS));
+        assert_eq!(fix(text, tokens, false), "helloWorld(HELL_YOU, how-are)");
+    }
+
+    #[test]
+    fn test_daemon_fallback() {
+        let socket: PathBuf = env::temp_dir().join(format!("spell_sweep_fallback_test_{}.sock", std::process::id()));
+        let _ = fs::remove_file(&socket);
+        let listener: UnixListener = UnixListener::bind(&socket).expect("Failed to bind the socket");
+        let client: Client = Client::connect(&socket).expect("Failed to connect to the socket");
+        // The daemon goes away before answering anything.
+        drop(listener);
+        fs::remove_file(&socket).expect("Failed to remove the socket");
+
+        let spell_check: SpellCheck = SpellCheck::with_daemon(client, || Ok(small_spell_check().with_limits(None, Some(1))));
+        assert!(spell_check.is_correct("hello"));
+        assert!(!spell_check.is_correct("wrold"));
+        assert_eq!(spell_check.suggest("wrold"), vec![("world".to_string(), 1)]);
+    }
 }
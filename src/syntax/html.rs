@@ -0,0 +1,440 @@
+use std::ops::Range;
+
+/// Attributes whose values are read by people, and so are checked by default.
+pub const ATTRIBUTES: &[&str] = &["alt", "title", "placeholder", "aria-label"];
+
+/// Elements whose content is code rather than prose.
+const SKIPPED_ELEMENTS: &[&str] = &["script", "style", "code", "pre"];
+
+/// Named character references that are decoded for checking. Any other named reference is
+/// treated as a word boundary.
+const ENTITIES: &[(&str, char)] = &[
+    ("amp", '&'),
+    ("lt", '<'),
+    ("gt", '>'),
+    ("quot", '"'),
+    ("apos", '\''),
+    ("nbsp", '\u{a0}'),
+    ("shy", '\u{ad}'),
+    ("ndash", '–'),
+    ("mdash", '—'),
+    ("lsquo", '‘'),
+    ("rsquo", '’'),
+    ("ldquo", '“'),
+    ("rdquo", '”'),
+    ("hellip", '…'),
+    ("copy", '©'),
+    ("reg", '®'),
+    ("trade", '™'),
+    ("Agrave", 'À'),
+    ("Aacute", 'Á'),
+    ("Acirc", 'Â'),
+    ("Atilde", 'Ã'),
+    ("Auml", 'Ä'),
+    ("Aring", 'Å'),
+    ("AElig", 'Æ'),
+    ("Ccedil", 'Ç'),
+    ("Egrave", 'È'),
+    ("Eacute", 'É'),
+    ("Ecirc", 'Ê'),
+    ("Euml", 'Ë'),
+    ("Igrave", 'Ì'),
+    ("Iacute", 'Í'),
+    ("Icirc", 'Î'),
+    ("Iuml", 'Ï'),
+    ("Ntilde", 'Ñ'),
+    ("Ograve", 'Ò'),
+    ("Oacute", 'Ó'),
+    ("Ocirc", 'Ô'),
+    ("Otilde", 'Õ'),
+    ("Ouml", 'Ö'),
+    ("Oslash", 'Ø'),
+    ("Ugrave", 'Ù'),
+    ("Uacute", 'Ú'),
+    ("Ucirc", 'Û'),
+    ("Uuml", 'Ü'),
+    ("Yacute", 'Ý'),
+    ("szlig", 'ß'),
+    ("agrave", 'à'),
+    ("aacute", 'á'),
+    ("acirc", 'â'),
+    ("atilde", 'ã'),
+    ("auml", 'ä'),
+    ("aring", 'å'),
+    ("aelig", 'æ'),
+    ("ccedil", 'ç'),
+    ("egrave", 'è'),
+    ("eacute", 'é'),
+    ("ecirc", 'ê'),
+    ("euml", 'ë'),
+    ("igrave", 'ì'),
+    ("iacute", 'í'),
+    ("icirc", 'î'),
+    ("iuml", 'ï'),
+    ("ntilde", 'ñ'),
+    ("ograve", 'ò'),
+    ("oacute", 'ó'),
+    ("ocirc", 'ô'),
+    ("otilde", 'õ'),
+    ("ouml", 'ö'),
+    ("oslash", 'ø'),
+    ("ugrave", 'ù'),
+    ("uacute", 'ú'),
+    ("ucirc", 'û'),
+    ("uuml", 'ü'),
+    ("yacute", 'ý'),
+    ("yuml", 'ÿ'),
+    ("OElig", 'Œ'),
+    ("oelig", 'œ'),
+];
+
+/// Returns the byte ranges of `text` that hold prose: text nodes and the values of the given
+/// attributes. Tags, comments, CDATA sections, processing instructions, template expressions
+/// like `{{ name }}` and the content of `<script>`, `<style>`, `<code>` and `<pre>` are left out.
+pub fn regions(text: &str, attributes: &[String]) -> Vec<Range<usize>> {
+    let mut regions: Vec<Range<usize>> = Vec::new();
+    let mut text_start: usize = 0;
+    let mut idx: usize = 0;
+
+    while let Some(offset) = text[idx..].find('<') {
+        let open: usize = idx + offset;
+        let rest: &str = &text[open..];
+
+        let tag_end: Option<usize> = if rest.starts_with("<!--") {
+            Some(skip_past(text, open, "-->"))
+        } else if rest.starts_with("<![CDATA[") {
+            Some(skip_past(text, open, "]]>"))
+        } else if rest.starts_with("<!") || rest.starts_with("<?") || rest.starts_with("</") {
+            Some(skip_past(text, open, ">"))
+        } else if rest[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            Some(start_tag(text, open, attributes, &mut regions))
+        } else {
+            None
+        };
+
+        match tag_end {
+            Some(tag_end) => {
+                text_regions(text, text_start, open, &mut regions);
+                idx = tag_end;
+                text_start = tag_end;
+            }
+            // A lone `<`, as in `a < b`, is part of the text.
+            None => idx = open + 1,
+        }
+    }
+    text_regions(text, text_start, text.len(), &mut regions);
+
+    regions.sort_by_key(|region| region.start);
+    regions
+}
+
+/// Returns the byte range of every element with a `lang` or `xml:lang` attribute, from the end
+/// of its start tag to the start of its end tag, along with the language. Nested elements come
+/// after the elements around them.
+pub fn language_sections(text: &str) -> Vec<(Range<usize>, String)> {
+    let mut sections: Vec<(Range<usize>, String)> = Vec::new();
+    // Open elements, with the index of their section if they have a language.
+    let mut open_elements: Vec<(String, Option<usize>)> = Vec::new();
+    let mut idx: usize = 0;
+
+    while let Some(offset) = text[idx..].find('<') {
+        let open: usize = idx + offset;
+        let rest: &str = &text[open..];
+        if rest.starts_with("<!--") {
+            idx = skip_past(text, open, "-->");
+            continue;
+        }
+        let tag_end: usize = skip_past(text, open, ">");
+        // A tag that is never closed runs to the end of the text, with no `>` to leave out.
+        let tag_stop: usize = if text[..tag_end].ends_with('>') { tag_end - 1 } else { tag_end };
+        let tag: &str = &text[open + 1..tag_stop.max(open + 1)];
+        idx = tag_end;
+
+        if let Some(name) = tag.strip_prefix('/') {
+            let name: String = name.trim().to_lowercase();
+            if let Some(position) = open_elements.iter().rposition(|(open_name, _)| *open_name == name) {
+                for (_, section) in open_elements.drain(position..) {
+                    if let Some(section) = section {
+                        sections[section].0.end = open;
+                    }
+                }
+            }
+            continue;
+        }
+        if !tag.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            continue;
+        }
+
+        let name: String = tag
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or("")
+            .to_lowercase();
+        // Elements without content have no section, whatever their language.
+        if tag.ends_with('/') || VOID_ELEMENTS.contains(&name.as_str()) {
+            continue;
+        }
+        let section: Option<usize> = language_attribute(tag).map(|lang| {
+            sections.push((tag_end..text.len(), lang));
+            sections.len() - 1
+        });
+        open_elements.push((name, section));
+    }
+
+    sections
+}
+
+/// Elements that have no end tag.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source", "track", "wbr",
+];
+
+/// Returns the value of the `lang` or `xml:lang` attribute of the start tag `tag`, given without
+/// its angle brackets.
+fn language_attribute(tag: &str) -> Option<String> {
+    let lower_tag: String = tag.to_lowercase();
+    for (position, _) in lower_tag.match_indices("lang") {
+        let before: &str = &lower_tag[..position];
+        if !(before.ends_with(char::is_whitespace) || before.ends_with(" xml:") || before.ends_with("\txml:")) {
+            continue;
+        }
+        let value: &str = tag[position + 4..].trim_start().strip_prefix('=')?.trim_start();
+        let value: &str = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => value[1..].split(quote).next().unwrap_or(""),
+            _ => value.split(|c: char| c.is_whitespace() || c == '/').next().unwrap_or(""),
+        };
+        return Some(value.to_string()).filter(|value| !value.is_empty());
+    }
+
+    None
+}
+
+fn skip_past(text: &str, start: usize, terminator: &str) -> usize {
+    text[start..]
+        .find(terminator)
+        .map_or(text.len(), |end| start + end + terminator.len())
+}
+
+/// Parses the start tag at `open`, records the values of checked attributes and returns the
+/// index just past the tag, or past the matching end tag of an element whose content is skipped.
+fn start_tag(text: &str, open: usize, attributes: &[String], regions: &mut Vec<Range<usize>>) -> usize {
+    let bytes: &[u8] = text.as_bytes();
+    let name_end: usize = text[open + 1..]
+        .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        .map_or(text.len(), |end| open + 1 + end);
+    let name: String = text[open + 1..name_end].to_lowercase();
+    let mut idx: usize = name_end;
+
+    while idx < bytes.len() && bytes[idx] != b'>' {
+        if bytes[idx].is_ascii_whitespace() || bytes[idx] == b'/' {
+            idx += 1;
+            continue;
+        }
+
+        let attribute_end: usize = text[idx..]
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '>' || c == '/')
+            .map_or(text.len(), |end| idx + end);
+        let attribute: String = text[idx..attribute_end].to_lowercase();
+        idx = attribute_end;
+        while idx < bytes.len() && bytes[idx].is_ascii_whitespace() {
+            idx += 1;
+        }
+        if bytes.get(idx) != Some(&b'=') {
+            continue;
+        }
+        idx += 1;
+        while idx < bytes.len() && bytes[idx].is_ascii_whitespace() {
+            idx += 1;
+        }
+
+        let (value_start, value_end, next): (usize, usize, usize) = match bytes.get(idx) {
+            Some(quote @ (b'"' | b'\'')) => {
+                let close: usize = text[idx + 1..]
+                    .find(*quote as char)
+                    .map_or(text.len(), |end| idx + 1 + end);
+                (idx + 1, close, (close + 1).min(text.len()))
+            }
+            _ => {
+                let end: usize = text[idx..]
+                    .find(|c: char| c.is_whitespace() || c == '>')
+                    .map_or(text.len(), |end| idx + end);
+                (idx, end, end)
+            }
+        };
+        if attributes.iter().any(|checked| checked.eq_ignore_ascii_case(&attribute)) {
+            text_regions(text, value_start, value_end, regions);
+        }
+        idx = next;
+    }
+
+    let tag_end: usize = (idx + 1).min(text.len());
+    let self_closing: bool = text[..idx].ends_with('/');
+    if self_closing || !SKIPPED_ELEMENTS.contains(&name.as_str()) {
+        return tag_end;
+    }
+
+    let closing_tag: String = format!("</{}", name);
+    text[tag_end..]
+        .to_lowercase()
+        .find(&closing_tag)
+        .map_or(text.len(), |close| skip_past(text, tag_end + close, ">"))
+}
+
+/// Pushes the ranges of text between `start` and `end`, leaving out template expressions.
+fn text_regions(text: &str, start: usize, end: usize, regions: &mut Vec<Range<usize>>) {
+    let mut region_start: usize = start;
+    let mut idx: usize = start;
+
+    while let Some(offset) = text[idx..end].find('{') {
+        let open: usize = idx + offset;
+        let terminator: Option<&str> = match text.as_bytes().get(open + 1) {
+            Some(b'{') => Some("}}"),
+            Some(b'%') => Some("%}"),
+            Some(b'#') => Some("#}"),
+            _ => None,
+        };
+
+        match terminator {
+            Some(terminator) => {
+                push_region(regions, region_start, open);
+                idx = text[open + 2..end]
+                    .find(terminator)
+                    .map_or(end, |close| open + 2 + close + terminator.len());
+                region_start = idx;
+            }
+            None => idx = open + 1,
+        }
+    }
+    push_region(regions, region_start, end);
+}
+
+fn push_region(regions: &mut Vec<Range<usize>>, start: usize, end: usize) {
+    if start < end {
+        regions.push(start..end);
+    }
+}
+
+/// Returns the length and value of the character reference at the start of `text`.
+fn entity(text: &str) -> Option<(usize, char)> {
+    let end: usize = text.find(';').filter(|end| *end <= 32)?;
+    let name: &str = &text[1..end];
+
+    let c: char = match name.strip_prefix('#') {
+        Some(number) => {
+            let code: u32 = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+            char::from_u32(code)?
+        }
+        None => ENTITIES.iter().find(|(entity, _)| *entity == name)?.1,
+    };
+    Some((end + 1, c))
+}
+
+/// Decodes the character references in `region` of `text`. Returns the decoded text along with
+/// the offset in `text` of every byte of it, plus one for the end, so words found in the
+/// decoded text can be mapped back onto the original.
+pub fn decode(text: &str, region: Range<usize>) -> (String, Vec<usize>) {
+    let mut decoded: String = String::with_capacity(region.len());
+    let mut offsets: Vec<usize> = Vec::with_capacity(region.len() + 1);
+    let mut idx: usize = region.start;
+
+    while idx < region.end {
+        let rest: &str = &text[idx..region.end];
+        let (length, c): (usize, char) = match rest.starts_with('&').then(|| entity(rest)).flatten() {
+            Some(reference) => reference,
+            // Unknown references split words rather than becoming part of them.
+            None if rest.starts_with('&') && rest[1..].starts_with(|c: char| c.is_alphanumeric() || c == '#') => {
+                (rest.find(';').map_or(1, |end| end + 1), ' ')
+            }
+            None => {
+                let c: char = rest.chars().next().expect("region is not empty");
+                (c.len_utf8(), c)
+            }
+        };
+
+        offsets.extend(std::iter::repeat_n(idx, c.len_utf8()));
+        decoded.push(c);
+        idx += length;
+    }
+    offsets.push(region.end);
+
+    (decoded, offsets)
+}
+
+/// Encodes a correction the way the text it replaces was encoded: characters that were written
+/// as references in `original` are written the same way again, and `&` and `<` are escaped.
+pub fn encode_like(original: &str, replacement: &str) -> String {
+    let mut references: Vec<(char, &str)> = Vec::new();
+    for (idx, _) in original.match_indices('&') {
+        if let Some((length, c)) = entity(&original[idx..]) {
+            references.push((c, &original[idx..idx + length]));
+        }
+    }
+
+    replacement
+        .chars()
+        .map(|c| match references.iter().find(|(referenced, _)| *referenced == c) {
+            Some((_, reference)) => reference.to_string(),
+            None if c == '&' => "&amp;".to_string(),
+            None if c == '<' => "&lt;".to_string(),
+            None => c.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode_like, language_sections, regions, ATTRIBUTES};
+
+    fn prose(text: &str) -> Vec<&str> {
+        let attributes: Vec<String> = ATTRIBUTES.iter().map(|attribute| attribute.to_string()).collect();
+        regions(text, &attributes).into_iter().map(|range| &text[range]).collect()
+    }
+
+    #[test]
+    fn test_regions() {
+        let text: &str = r#"<!DOCTYPE html>
+<html lang="en"><!-- a comment -->
+<img src="cat.png" alt="A sleepy cat" class=photo title=Cat>
+<p data-text="skipped">Hello {{ user.name }}, a &lt; b</p>
+<pre><code>let x = 1;</code></pre><script>var y;</script><br/>
+<input placeholder='Search here'><STYLE>p {}</STYLE>Done</html>"#;
+
+        assert_eq!(
+            prose(text),
+            vec!["\n", "\n", "A sleepy cat", "Cat", "\n", "Hello ", ", a &lt; b", "\n", "\n", "Search here", "Done"]
+        );
+    }
+
+    #[test]
+    fn test_language_sections() {
+        let text: &str = "<p lang=\"de\">Straße</p> <q lang=fr>café";
+        assert_eq!(
+            language_sections(text),
+            vec![(13..20, "de".to_string()), (36..text.len(), "fr".to_string())]
+        );
+        assert!(language_sections("Use a < b in café").is_empty());
+        assert!(language_sections("<😀").is_empty());
+    }
+
+    #[test]
+    fn test_decode() {
+        let text: &str = "caf&eacute; don&#39;t&nbsp;go &bogus; x";
+        let (decoded, offsets) = decode(text, 0..text.len());
+
+        assert_eq!(decoded, "café don't\u{a0}go   x");
+        assert_eq!(offsets.len(), decoded.len() + 1);
+        assert_eq!(offsets[3], 3);
+        assert_eq!(offsets[5], 11);
+        assert_eq!(*offsets.last().unwrap(), text.len());
+    }
+
+    #[test]
+    fn test_encode_like() {
+        assert_eq!(encode_like("caf&eacute;s", "cafés"), "caf&eacute;s");
+        assert_eq!(encode_like("don&#39;t", "won't"), "won&#39;t");
+        assert_eq!(encode_like("Tom", "Tom & Jerry"), "Tom &amp; Jerry");
+    }
+}
@@ -0,0 +1,263 @@
+use std::ops::Range;
+
+/// Commands whose arguments are prose, with the number of leading arguments that are not, like
+/// the URL of `\href{url}{text}`.
+const PROSE_COMMANDS: &[(&str, usize)] = &[
+    ("part", 0),
+    ("chapter", 0),
+    ("section", 0),
+    ("subsection", 0),
+    ("subsubsection", 0),
+    ("paragraph", 0),
+    ("subparagraph", 0),
+    ("title", 0),
+    ("subtitle", 0),
+    ("caption", 0),
+    ("footnote", 0),
+    ("footnotetext", 0),
+    ("marginpar", 0),
+    ("thanks", 0),
+    ("emph", 0),
+    ("textbf", 0),
+    ("textit", 0),
+    ("textsl", 0),
+    ("textsc", 0),
+    ("textup", 0),
+    ("textmd", 0),
+    ("textrm", 0),
+    ("textsf", 0),
+    ("underline", 0),
+    ("uline", 0),
+    ("mbox", 0),
+    ("fbox", 0),
+    ("text", 0),
+    ("intertext", 0),
+    ("item", 0),
+    ("href", 1),
+];
+
+/// Environments whose content is math.
+const MATH_ENVIRONMENTS: &[&str] = &[
+    "math",
+    "displaymath",
+    "equation",
+    "equation*",
+    "align",
+    "align*",
+    "alignat",
+    "alignat*",
+    "flalign",
+    "flalign*",
+    "gather",
+    "gather*",
+    "multline",
+    "multline*",
+    "eqnarray",
+    "eqnarray*",
+];
+
+/// Environments whose content is code or otherwise not prose.
+const VERBATIM_ENVIRONMENTS: &[&str] = &[
+    "verbatim",
+    "verbatim*",
+    "Verbatim",
+    "lstlisting",
+    "minted",
+    "comment",
+    "alltt",
+    "tikzpicture",
+];
+
+/// Returns the byte ranges of `text` that hold prose. Control sequences and their arguments,
+/// inline and display math, comments and verbatim environments are left out, except for the
+/// text arguments of commands like `\section{...}` and `\emph{...}`.
+pub fn regions(text: &str) -> Vec<Range<usize>> {
+    let mut regions: Vec<Range<usize>> = Vec::new();
+    scan(text, 0, text.len(), &mut regions);
+    regions
+}
+
+/// Pushes the prose ranges between `start` and `end`.
+fn scan(text: &str, start: usize, end: usize, regions: &mut Vec<Range<usize>>) {
+    let bytes: &[u8] = text.as_bytes();
+    let mut region_start: usize = start;
+    let mut idx: usize = start;
+
+    while idx < end {
+        let skip_to: Option<usize> = match bytes[idx] {
+            b'%' => Some(text[idx..end].find('\n').map_or(end, |line_end| idx + line_end)),
+            b'$' if bytes.get(idx + 1) == Some(&b'$') => Some(skip_past(text, idx + 2, end, "$$")),
+            b'$' => Some(skip_past_unescaped(text, idx + 1, end, b'$')),
+            b'\\' => {
+                // Prose arguments are pushed while the command is read, so the text before it goes first.
+                push_region(regions, region_start, idx);
+                region_start = idx;
+                Some(command(text, idx, end, regions))
+            }
+            // Braces, `~` ties and `&` column separators separate words.
+            b'{' | b'}' | b'~' | b'&' => Some(idx + 1),
+            _ => None,
+        };
+
+        match skip_to {
+            Some(skip_to) => {
+                push_region(regions, region_start, idx);
+                idx = skip_to.min(end);
+                region_start = idx;
+            }
+            None => idx += 1,
+        }
+    }
+    push_region(regions, region_start, end);
+}
+
+/// Handles the control sequence at `idx`, scanning any prose arguments it has, and returns the
+/// index just past it and its arguments.
+fn command(text: &str, idx: usize, end: usize, regions: &mut Vec<Range<usize>>) -> usize {
+    let bytes: &[u8] = text.as_bytes();
+    let name_length: usize = bytes[idx + 1..end].iter().take_while(|byte| byte.is_ascii_alphabetic()).count();
+
+    if name_length == 0 {
+        return match bytes.get(idx + 1) {
+            Some(b'(') => skip_past(text, idx + 2, end, "\\)"),
+            Some(b'[') => skip_past(text, idx + 2, end, "\\]"),
+            // A control symbol like `\%` or `\\`.
+            Some(_) => idx + 1 + text[idx + 1..].chars().next().map_or(0, char::len_utf8),
+            None => end,
+        };
+    }
+
+    let name: &str = &text[idx + 1..idx + 1 + name_length];
+    let mut next: usize = idx + 1 + name_length;
+    if bytes.get(next) == Some(&b'*') {
+        next += 1;
+    }
+
+    match name {
+        "verb" | "lstinline" if next < end => {
+            let delimiter: u8 = bytes[next];
+            let closing: u8 = if delimiter == b'{' { b'}' } else { delimiter };
+            return skip_past_unescaped(text, next + 1, end, closing);
+        }
+        "begin" => {
+            let Some(close) = argument_end(text, next, end) else {
+                return next;
+            };
+            let environment: &str = &text[next + 1..close - 1];
+            if MATH_ENVIRONMENTS.contains(&environment) || VERBATIM_ENVIRONMENTS.contains(&environment) {
+                return skip_past(text, close, end, &format!("\\end{{{}}}", environment));
+            }
+            return skip_arguments(text, close, end);
+        }
+        _ => {}
+    }
+
+    let Some((_, skipped)) = PROSE_COMMANDS.iter().find(|(command, _)| *command == name) else {
+        return skip_arguments(text, next, end);
+    };
+
+    let mut argument: usize = 0;
+    while let Some(close) = argument_end(text, next, end) {
+        if argument >= *skipped {
+            scan(text, next + 1, close - 1, regions);
+        }
+        argument += 1;
+        next = close;
+    }
+    next
+}
+
+/// Returns the index just past the `{...}` or `[...]` argument starting at `idx`, if there is one.
+fn argument_end(text: &str, idx: usize, end: usize) -> Option<usize> {
+    let bytes: &[u8] = text.as_bytes();
+    let (opening, closing): (u8, u8) = match bytes.get(idx) {
+        Some(b'{') => (b'{', b'}'),
+        Some(b'[') => (b'[', b']'),
+        _ => return None,
+    };
+    let mut depth: usize = 0;
+    let mut pos: usize = idx;
+
+    while pos < end {
+        match bytes[pos] {
+            b'\\' => pos += 1,
+            byte if byte == opening => depth += 1,
+            byte if byte == closing => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(pos + 1);
+                }
+            }
+            _ => {}
+        }
+        pos += 1;
+    }
+    Some(end)
+}
+
+fn skip_arguments(text: &str, mut idx: usize, end: usize) -> usize {
+    while let Some(close) = argument_end(text, idx, end) {
+        idx = close;
+    }
+    idx
+}
+
+fn skip_past(text: &str, start: usize, end: usize, terminator: &str) -> usize {
+    text[start.min(end)..end]
+        .find(terminator)
+        .map_or(end, |close| start + close + terminator.len())
+}
+
+fn skip_past_unescaped(text: &str, start: usize, end: usize, terminator: u8) -> usize {
+    let bytes: &[u8] = text.as_bytes();
+    let mut idx: usize = start;
+
+    while idx < end {
+        match bytes[idx] {
+            b'\\' => idx += 2,
+            byte if byte == terminator => return idx + 1,
+            _ => idx += 1,
+        }
+    }
+    end
+}
+
+fn push_region(regions: &mut Vec<Range<usize>>, start: usize, end: usize) {
+    if start < end {
+        regions.push(start..end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::regions;
+
+    fn prose(text: &str) -> Vec<&str> {
+        regions(text)
+            .into_iter()
+            .map(|range| text[range].trim())
+            .filter(|prose| !prose.is_empty())
+            .collect()
+    }
+
+    #[test]
+    fn test_commands() {
+        let text: &str = "\\section{Intro} As shown in~\\cite{knuth84}, see \\ref{fig:a}.\n\
+                          \\emph{Really \\textbf{bold}} \\href{https://x.org}{our site} 50\\% done\\\\";
+
+        assert_eq!(
+            prose(text),
+            vec!["Intro", "As shown in", ", see", ".", "Really", "bold", "our site", "50", "done"]
+        );
+    }
+
+    #[test]
+    fn test_math_comments_and_verbatim() {
+        let text: &str = "Inline $x^2$ and \\(y\\) math. % a coment\n\
+                          $$\\sum$$ \\[z\\] \\begin{equation} a = b \\end{equation}\n\
+                          \\begin{itemize}[noitemsep]\\item First\\end{itemize}\n\
+                          \\begin{verbatim}raw txt\\end{verbatim} \\verb|code| \\verb+more+ End";
+
+        assert_eq!(prose(text), vec!["Inline", "and", "math.", "First", "End"]);
+    }
+}
@@ -0,0 +1,268 @@
+use std::ops::Range;
+
+/// Returns the byte ranges of `text` that hold prose. Front matter, fenced and indented code
+/// blocks, HTML blocks and comments, link reference definitions, inline code, autolinks, inline
+/// HTML tags, bare URLs and link destinations are left out.
+pub fn regions(text: &str) -> Vec<Range<usize>> {
+    let mut regions: Vec<Range<usize>> = Vec::new();
+    let lines: Vec<(usize, &str)> = lines_with_offsets(text);
+    let mut idx: usize = skip_front_matter(&lines);
+    let mut previous_blank: bool = true;
+
+    while idx < lines.len() {
+        let (offset, line) = lines[idx];
+        let content: &str = line.trim_end_matches(['\n', '\r']);
+        let trimmed: &str = content.trim_start();
+        let indent: usize = content.len() - trimmed.len();
+        let indent_width: usize = content[..indent].chars().map(|c| if c == '\t' { 4 } else { 1 }).sum();
+
+        if trimmed.is_empty() {
+            previous_blank = true;
+            idx += 1;
+            continue;
+        }
+
+        if indent_width <= 3 {
+            if let Some(fence) = fence_marker(trimmed) {
+                idx = skip_fenced_block(&lines, idx + 1, fence);
+                previous_blank = false;
+                continue;
+            }
+            if trimmed.starts_with("<!--") {
+                idx = skip_until(&lines, idx, "-->");
+                previous_blank = false;
+                continue;
+            }
+            if is_html_block_start(trimmed) {
+                while idx < lines.len() && !lines[idx].1.trim().is_empty() {
+                    idx += 1;
+                }
+                continue;
+            }
+            if is_link_reference_definition(trimmed) {
+                idx += 1;
+                previous_blank = false;
+                continue;
+            }
+        } else if previous_blank {
+            // Indented code block: runs until a non-blank line that is indented less.
+            while idx < lines.len() {
+                let next: &str = lines[idx].1.trim_end_matches(['\n', '\r']);
+                if !next.trim().is_empty() && !next.starts_with("    ") && !next.starts_with('\t') {
+                    break;
+                }
+                idx += 1;
+            }
+            previous_blank = true;
+            continue;
+        }
+
+        inline_regions(text, offset + indent, offset + content.len(), &mut regions);
+        previous_blank = false;
+        idx += 1;
+    }
+
+    regions
+}
+
+fn lines_with_offsets(text: &str) -> Vec<(usize, &str)> {
+    let mut offset: usize = 0;
+    text.split_inclusive('\n')
+        .map(|line| {
+            let start: usize = offset;
+            offset += line.len();
+            (start, line)
+        })
+        .collect()
+}
+
+/// Returns the index of the first line after a leading `---` or `+++` front matter block.
+fn skip_front_matter(lines: &[(usize, &str)]) -> usize {
+    let delimiter: &str = match lines.first().map(|(_, line)| line.trim_end()) {
+        Some("---") => "---",
+        Some("+++") => "+++",
+        _ => return 0,
+    };
+
+    for (idx, (_, line)) in lines.iter().enumerate().skip(1) {
+        let line: &str = line.trim_end();
+        if line == delimiter || (delimiter == "---" && line == "...") {
+            return idx + 1;
+        }
+    }
+    0
+}
+
+/// Returns the `lang` or `language` key of a leading YAML or TOML front matter block.
+pub fn front_matter_language(text: &str) -> Option<String> {
+    let lines: Vec<(usize, &str)> = lines_with_offsets(text);
+    let end: usize = skip_front_matter(&lines);
+
+    lines.get(1..end.saturating_sub(1))?.iter().find_map(|(_, line)| {
+        let (key, value) = line.split_once(':').or_else(|| line.split_once('='))?;
+        let value: &str = value.trim().trim_matches(['"', '\'']);
+        (matches!(key.trim(), "lang" | "language") && !value.is_empty()).then(|| value.to_string())
+    })
+}
+
+/// Returns the fence character and length if `line` opens a fenced code block.
+pub(super) fn fence_marker(line: &str) -> Option<(char, usize)> {
+    let fence_char: char = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let length: usize = line.chars().take_while(|c| *c == fence_char).count();
+
+    if length < 3 || (fence_char == '`' && line[length..].contains('`')) {
+        return None;
+    }
+    Some((fence_char, length))
+}
+
+fn skip_fenced_block(lines: &[(usize, &str)], mut idx: usize, fence: (char, usize)) -> usize {
+    while idx < lines.len() {
+        let trimmed: &str = lines[idx].1.trim();
+        let length: usize = trimmed.chars().take_while(|c| *c == fence.0).count();
+        idx += 1;
+        if length >= fence.1 && trimmed.chars().all(|c| c == fence.0) {
+            break;
+        }
+    }
+    idx
+}
+
+fn skip_until(lines: &[(usize, &str)], mut idx: usize, terminator: &str) -> usize {
+    while idx < lines.len() {
+        idx += 1;
+        if lines[idx - 1].1.contains(terminator) {
+            break;
+        }
+    }
+    idx
+}
+
+fn is_html_block_start(line: &str) -> bool {
+    let rest: &str = match line.strip_prefix('<') {
+        Some(rest) => rest.strip_prefix('/').unwrap_or(rest),
+        None => return false,
+    };
+    let name_length: usize = rest.chars().take_while(|c| c.is_ascii_alphanumeric()).count();
+
+    name_length > 0
+        && rest.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+        && rest[name_length..].starts_with(|c: char| c == '>' || c == '/' || c.is_whitespace())
+}
+
+fn is_link_reference_definition(line: &str) -> bool {
+    line.starts_with('[') && !line.starts_with("[^") && line.find("]:").is_some_and(|idx| idx > 1)
+}
+
+/// Finds the byte index just past the delimiter that closes the one opened at `open`, skipping
+/// nested pairs.
+fn find_closing(text: &str, open: usize, opening: u8, closing: u8, end: usize) -> Option<usize> {
+    let bytes: &[u8] = text.as_bytes();
+    let mut depth: usize = 0;
+    let mut idx: usize = open;
+
+    while idx < end {
+        match bytes[idx] {
+            b'\\' => idx += 1,
+            byte if byte == opening => depth += 1,
+            byte if byte == closing => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(idx + 1);
+                }
+            }
+            _ => {}
+        }
+        idx += 1;
+    }
+    None
+}
+
+/// Pushes the prose ranges of the inline content between `start` and `end`.
+pub(super) fn inline_regions(text: &str, start: usize, end: usize, regions: &mut Vec<Range<usize>>) {
+    let bytes: &[u8] = text.as_bytes();
+    let mut region_start: usize = start;
+    let mut idx: usize = start;
+
+    while idx < end {
+        let skip_to: Option<usize> = match bytes[idx] {
+            b'\\' if idx + 1 < end => Some(idx + 1 + text[idx + 1..].chars().next().map_or(1, char::len_utf8)),
+            b'`' => {
+                let run: usize = bytes[idx..end].iter().take_while(|b| **b == b'`').count();
+                let fence: &str = &text[idx..idx + run];
+                match text[idx + run..end].find(fence) {
+                    Some(close) => Some(idx + run + close + run),
+                    None => Some(idx + run),
+                }
+            }
+            b'<' => text[idx..end]
+                .find('>')
+                .filter(|close| {
+                    let inner: &str = &text[idx + 1..idx + close];
+                    !inner.is_empty() && !inner.contains(char::is_whitespace) || is_html_block_start(&text[idx..idx + close + 1])
+                })
+                .map(|close| idx + close + 1),
+            b']' if bytes.get(idx + 1) == Some(&b'(') => find_closing(text, idx + 1, b'(', b')', end),
+            b']' if bytes.get(idx + 1) == Some(&b'[') => find_closing(text, idx + 1, b'[', b']', end),
+            b'h' | b'w' if is_url_start(&text[idx..end]) && (idx == start || !bytes[idx - 1].is_ascii_alphanumeric()) => {
+                Some(idx + text[idx..end].find(char::is_whitespace).unwrap_or(end - idx))
+            }
+            _ => None,
+        };
+
+        match skip_to {
+            Some(skip_to) => {
+                let skip_from: usize = if bytes[idx] == b']' { idx + 1 } else { idx };
+                push_region(regions, region_start, skip_from);
+                idx = skip_to.min(end);
+                region_start = idx;
+            }
+            None => idx += 1,
+        }
+    }
+    push_region(regions, region_start, end);
+}
+
+fn push_region(regions: &mut Vec<Range<usize>>, start: usize, end: usize) {
+    if start < end {
+        regions.push(start..end);
+    }
+}
+
+fn is_url_start(text: &str) -> bool {
+    text.starts_with("http://") || text.starts_with("https://") || text.starts_with("www.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::regions;
+
+    fn prose(text: &str) -> Vec<&str> {
+        regions(text).into_iter().map(|range| &text[range]).collect()
+    }
+
+    #[test]
+    fn test_blocks() {
+        let text: &str = "---\ntitle: Front mattr\n---\n# Heading\n\n```rust\nlet x = 1;\n```\n\n    indented cod\n\nSome text\n<div class=\"x\">\nhtml blok\n</div>\n\n<!-- a\ncomment -->\n[ref]: https://example.com\nEnd\n";
+
+        assert_eq!(prose(text), vec!["# Heading", "Some text", "End"]);
+    }
+
+    #[test]
+    fn test_inline() {
+        let text: &str = "Use `cargo bild` or see [the docs](https://exmple.com \"titel\") and ![alt txt](img.png).\n";
+        assert_eq!(prose(text), vec!["Use ", " or see [the docs]", " and ![alt txt]", "."]);
+
+        let text: &str = "Visit <https://exmple.com>, https://exmple.org/path or [text][refrence] \\*not\\* <span>html</span>\n";
+        assert_eq!(prose(text), vec!["Visit ", ", ", " or [text]", " ", "not", " ", "html"]);
+    }
+
+    #[test]
+    fn test_offsets() {
+        let text: &str = "Intro\n\n* item with `code` here\n";
+        let ranges = regions(text);
+
+        assert_eq!(ranges, vec![0..5, 7..19, 25..30]);
+        assert_eq!(&text[ranges[2].clone()], " here");
+    }
+}
@@ -0,0 +1,139 @@
+use std::{ops::Range, path::Path};
+
+pub mod html;
+pub mod latex;
+pub mod markdown;
+pub mod source;
+
+use source::Language;
+
+use crate::processor::{self, Token, TokenClass};
+
+/// Settings that change which parts of a document are checked.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Options {
+    /// Checks the compound identifiers in source code as well as comments and strings.
+    pub identifiers: bool,
+    /// HTML and XML attributes whose values are checked.
+    pub attributes: Vec<String>,
+    /// Classes of tokens, such as URLs and numbers, that are not checked.
+    pub skipped_tokens: Vec<TokenClass>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            identifiers: false,
+            attributes: html::ATTRIBUTES.iter().map(|attribute| attribute.to_string()).collect(),
+            skipped_tokens: processor::NON_WORDS.to_vec(),
+        }
+    }
+}
+
+/// The kind of document being checked, which decides which parts of it are prose.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Syntax {
+    Text,
+    Markdown,
+    /// Text nodes and human-readable attributes of HTML and XML.
+    Html,
+    /// Prose of LaTeX documents.
+    Latex,
+    /// Comments and string literals of source code.
+    Source(Language),
+}
+
+impl Syntax {
+    pub fn from_path(path: &Path) -> Self {
+        let extension: String = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+
+        match extension.as_str() {
+            "md" | "markdown" | "mdown" | "mkd" | "mdx" => Syntax::Markdown,
+            "html" | "htm" | "xhtml" | "shtml" | "xml" | "svg" | "xsl" | "xslt" | "rss" | "atom" | "hbs"
+            | "handlebars" | "mustache" | "j2" | "jinja" | "jinja2" | "njk" => Syntax::Html,
+            "tex" | "latex" | "ltx" => Syntax::Latex,
+            _ => Language::from_path(path).map_or(Syntax::Text, Syntax::Source),
+        }
+    }
+
+    /// Whether every line can be checked on its own, which lets the input be streamed.
+    pub fn is_line_based(&self) -> bool {
+        matches!(self, Syntax::Text)
+    }
+
+    /// Returns the byte ranges of `text` that should be spell checked.
+    pub fn regions(&self, text: &str, options: &Options) -> Vec<Range<usize>> {
+        match self {
+            Syntax::Text => std::iter::once(0..text.len()).collect(),
+            Syntax::Markdown => markdown::regions(text),
+            Syntax::Html => html::regions(text, &options.attributes),
+            Syntax::Latex => latex::regions(text),
+            Syntax::Source(language) => source::regions(text, *language, options.identifiers),
+        }
+    }
+
+    /// Returns the words of `text` that should be spell checked. Character references in HTML are
+    /// decoded, so a token's word may differ from the text its span covers.
+    pub fn tokenize(&self, text: &str, options: &Options) -> Vec<Token> {
+        let regions: Vec<Range<usize>> = self.regions(text, options);
+
+        match self {
+            Syntax::Html => regions
+                .into_iter()
+                .flat_map(|region| {
+                    let (decoded, offsets) = html::decode(text, region);
+                    processor::tokenize_decoded(&decoded, &offsets, &options.skipped_tokens)
+                })
+                .collect(),
+            _ => processor::tokenize_regions(text, &regions, &options.skipped_tokens),
+        }
+    }
+
+    /// Encodes a correction for the `original` text it replaces.
+    pub fn encode(&self, original: &str, replacement: &str) -> String {
+        match self {
+            Syntax::Html => html::encode_like(original, replacement),
+            _ => replacement.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{source::Language, Options, Syntax};
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(Syntax::from_path(Path::new("README.md")), Syntax::Markdown);
+        assert_eq!(Syntax::from_path(Path::new("docs/guide.MARKDOWN")), Syntax::Markdown);
+        assert_eq!(Syntax::from_path(Path::new("notes.txt")), Syntax::Text);
+        assert_eq!(Syntax::from_path(Path::new("LICENSE")), Syntax::Text);
+        assert_eq!(Syntax::from_path(Path::new("templates/index.html.j2")), Syntax::Html);
+        assert_eq!(Syntax::from_path(Path::new("feed.XML")), Syntax::Html);
+        assert_eq!(Syntax::from_path(Path::new("paper/main.tex")), Syntax::Latex);
+        assert_eq!(Syntax::from_path(Path::new("src/main.rs")), Syntax::Source(Language::Rust));
+        assert_eq!(Syntax::from_path(Path::new("app.tsx")), Syntax::Source(Language::JavaScript));
+        assert_eq!(Syntax::from_path(Path::new("include/lib.hpp")), Syntax::Source(Language::C));
+    }
+
+    #[test]
+    fn test_html_tokens() {
+        let text: &str = "<p title=\"Caf&eacute; menu\">Don&#39;t&nbsp;mis it</p>";
+        let syntax: Syntax = Syntax::Html;
+        let tokens = syntax.tokenize(text, &Options::default());
+
+        assert_eq!(
+            tokens.iter().map(|token| token.word.as_str()).collect::<Vec<&str>>(),
+            vec!["Café", "menu", "Don't", "mis", "it"]
+        );
+        assert_eq!(&text[tokens[0].start..tokens[0].end], "Caf&eacute;");
+        assert_eq!(&text[tokens[2].start..tokens[2].end], "Don&#39;t");
+        assert_eq!(syntax.encode("Caf&eacute;", "Cafés"), "Caf&eacute;s");
+    }
+}
@@ -0,0 +1,560 @@
+use std::{ops::Range, path::Path};
+
+use super::markdown;
+use crate::processor;
+
+/// A programming language whose comments and string literals can be checked.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Language {
+    Rust,
+    Python,
+    /// JavaScript and TypeScript.
+    JavaScript,
+    Go,
+    /// C and C++.
+    C,
+    Shell,
+}
+
+impl Language {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension: String = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+
+        match extension.as_str() {
+            "rs" => Some(Language::Rust),
+            "py" | "pyi" | "pyw" => Some(Language::Python),
+            "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "mts" | "cts" => Some(Language::JavaScript),
+            "go" => Some(Language::Go),
+            "c" | "h" | "cc" | "cpp" | "cxx" | "c++" | "hh" | "hpp" | "hxx" | "h++" | "m" | "mm" => Some(Language::C),
+            "sh" | "bash" | "zsh" | "ksh" | "dash" => Some(Language::Shell),
+            _ => None,
+        }
+    }
+
+    fn line_comment(&self) -> &'static str {
+        match self {
+            Language::Python | Language::Shell => "#",
+            _ => "//",
+        }
+    }
+
+    fn has_block_comments(&self) -> bool {
+        !matches!(self, Language::Python | Language::Shell)
+    }
+}
+
+/// How code is embedded in a string literal, so it can be left out of the checked text.
+#[derive(Clone, Copy, PartialEq)]
+enum Interpolation {
+    None,
+    /// Python f-strings: `{expression}`, with `{{` and `}}` as literal braces.
+    Braces,
+    /// JavaScript template literals: `${expression}`.
+    DollarBraces,
+    /// Shell double quotes: `$name`, `${name}`, `$(command)` and backticks.
+    Shell,
+}
+
+struct Lexer<'a> {
+    text: &'a str,
+    bytes: &'a [u8],
+    language: Language,
+    regions: Vec<Range<usize>>,
+    /// Whether the lexer is inside a fenced code block of a doc comment.
+    in_doc_fence: bool,
+    /// Whether compound identifiers in code are checked too.
+    identifiers: bool,
+}
+
+/// Returns the byte ranges of comments and string literals in `text`. Escape sequences,
+/// interpolated expressions and code blocks inside doc comments are left out, so every range
+/// maps one to one onto the source. With `identifiers`, identifiers in code that are made of
+/// several words, like `recieveBuffer` or `MAX_LENGHT`, are included as well; single-word
+/// identifiers are left alone, since most of them are keywords and library names.
+pub fn regions(text: &str, language: Language, identifiers: bool) -> Vec<Range<usize>> {
+    let mut lexer: Lexer = Lexer {
+        text,
+        bytes: text.as_bytes(),
+        language,
+        regions: Vec::new(),
+        in_doc_fence: false,
+        identifiers,
+    };
+    lexer.run();
+    lexer.regions
+}
+
+fn is_identifier_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_' || byte >= 0x80
+}
+
+impl Lexer<'_> {
+    fn run(&mut self) {
+        let mut idx: usize = 0;
+
+        if self.text.starts_with("#!") {
+            idx = self.line_end(0);
+        }
+
+        while idx < self.bytes.len() {
+            idx = match self.bytes[idx] {
+                b'\n' | b' ' | b'\t' | b'\r' => idx + 1,
+                _ if self.is_line_comment(idx) => self.line_comment(idx),
+                b'/' if self.language.has_block_comments() && self.bytes.get(idx + 1) == Some(&b'*') => {
+                    self.block_comment(idx)
+                }
+                b'#' if self.language == Language::C && self.is_include(idx) => self.line_end(idx),
+                _ => match self.string_start(idx) {
+                    Some(next) => next,
+                    None if self.identifiers && !self.follows_identifier(idx) && !self.bytes[idx].is_ascii_digit() => {
+                        self.identifier(idx)
+                    }
+                    None => idx + self.char_length(idx),
+                },
+            };
+        }
+    }
+
+    /// Skips the identifier or other code character at `idx`, recording compound identifiers.
+    fn identifier(&mut self, idx: usize) -> usize {
+        let length: usize = self.bytes[idx..]
+            .iter()
+            .take_while(|byte| is_identifier_byte(**byte))
+            .count();
+        if length == 0 {
+            return idx + self.char_length(idx);
+        }
+
+        let end: usize = idx + length;
+        if processor::identifier_parts(&self.text[idx..end]).len() > 1 {
+            self.push_region(idx, end);
+        }
+        end
+    }
+
+    fn char_length(&self, idx: usize) -> usize {
+        self.text[idx..].chars().next().map_or(1, char::len_utf8)
+    }
+
+    fn line_end(&self, idx: usize) -> usize {
+        self.text[idx..].find('\n').map_or(self.bytes.len(), |end| idx + end)
+    }
+
+    fn follows_identifier(&self, idx: usize) -> bool {
+        idx > 0 && is_identifier_byte(self.bytes[idx - 1])
+    }
+
+    fn is_line_comment(&self, idx: usize) -> bool {
+        let marker: &str = self.language.line_comment();
+        if !self.text[idx..].starts_with(marker) {
+            return false;
+        }
+
+        // `#` only starts a shell comment at the beginning of a word, unlike `$#` or `${#name}`.
+        self.language != Language::Shell
+            || idx == 0
+            || matches!(self.bytes[idx - 1], b' ' | b'\t' | b'\n' | b';' | b'|' | b'&' | b'(')
+    }
+
+    fn is_include(&self, idx: usize) -> bool {
+        let directive: &str = self.text[idx + 1..].trim_start_matches([' ', '\t']);
+        directive.starts_with("include") || directive.starts_with("import")
+    }
+
+    fn line_comment(&mut self, idx: usize) -> usize {
+        let end: usize = self.line_end(idx);
+        let rest: &str = &self.text[idx..end];
+        let is_doc: bool = self.language == Language::Rust && (rest.starts_with("///") || rest.starts_with("//!"));
+        let marker_length: usize = rest.bytes().take_while(|byte| *byte == rest.as_bytes()[0]).count();
+        let content_start: usize = idx + marker_length + usize::from(is_doc && rest[marker_length..].starts_with('!'));
+
+        self.comment_line(content_start, end, is_doc);
+        end
+    }
+
+    fn block_comment(&mut self, idx: usize) -> usize {
+        let nested: bool = self.language == Language::Rust;
+        let is_doc: bool = self.language == Language::Rust
+            && (self.text[idx..].starts_with("/**") && !self.text[idx..].starts_with("/**/")
+                || self.text[idx..].starts_with("/*!"));
+        let mut depth: usize = 0;
+        let mut end: usize = idx;
+
+        while end < self.bytes.len() {
+            // Bytes rather than text, since `end` steps into the middle of multibyte characters.
+            if self.bytes[end..].starts_with(b"/*") && (nested || depth == 0) {
+                depth += 1;
+                end += 2;
+            } else if self.bytes[end..].starts_with(b"*/") {
+                depth = depth.saturating_sub(1);
+                end += 2;
+                if depth == 0 {
+                    break;
+                }
+            } else {
+                end += 1;
+            }
+        }
+
+        let content_end: usize = if self.text[..end].ends_with("*/") { end - 2 } else { end };
+        let mut line_start: usize = idx + 2 + usize::from(is_doc);
+        while line_start < content_end {
+            let line_end: usize = self.text[line_start..content_end]
+                .find('\n')
+                .map_or(content_end, |offset| line_start + offset);
+            let decoration: usize = self.text[line_start..line_end]
+                .find(|c: char| !c.is_whitespace() && c != '*')
+                .unwrap_or(line_end - line_start);
+            self.comment_line(line_start + decoration, line_end, is_doc);
+            line_start = line_end + 1;
+        }
+
+        end
+    }
+
+    /// Checks one line of comment text. Comments are treated as markdown prose, and in doc
+    /// comments fenced code blocks are skipped entirely.
+    fn comment_line(&mut self, start: usize, end: usize, is_doc: bool) {
+        if is_doc && markdown::fence_marker(self.text[start..end].trim()).is_some() {
+            self.in_doc_fence = !self.in_doc_fence;
+            return;
+        }
+        if is_doc && self.in_doc_fence {
+            return;
+        }
+        markdown::inline_regions(self.text, start, end, &mut self.regions);
+    }
+
+    /// If a string or character literal starts at `idx`, records its text and returns the index
+    /// just past it.
+    fn string_start(&mut self, idx: usize) -> Option<usize> {
+        let rest: &str = &self.text[idx..];
+
+        match self.language {
+            Language::Rust => {
+                if self.follows_identifier(idx) {
+                    return None;
+                }
+                let prefix: usize = if rest.starts_with("br") || rest.starts_with("cr") {
+                    2
+                } else if rest.starts_with('r') {
+                    1
+                } else {
+                    0
+                };
+                if prefix > 0 {
+                    let hashes: usize = rest[prefix..].bytes().take_while(|byte| *byte == b'#').count();
+                    if rest[prefix + hashes..].starts_with('"') {
+                        let close: String = format!("\"{}", "#".repeat(hashes));
+                        return Some(self.string_body(idx + prefix + hashes + 1, &close, false, Interpolation::None));
+                    }
+                }
+                let prefix: usize = usize::from(rest.starts_with("b\"") || rest.starts_with("c\""));
+                match rest.as_bytes()[prefix] {
+                    b'"' => Some(self.string_body(idx + prefix + 1, "\"", true, Interpolation::None)),
+                    b'\'' => Some(self.char_literal(idx + prefix)),
+                    _ => None,
+                }
+            }
+            Language::Python => {
+                if self.follows_identifier(idx) {
+                    return None;
+                }
+                let prefix: usize = rest
+                    .bytes()
+                    .take(3)
+                    .take_while(|byte| b"rRbBuUfF".contains(byte))
+                    .count();
+                let flags: String = rest[..prefix].to_lowercase();
+                let quoted: &str = &rest[prefix..];
+                let quote: &str = ["\"\"\"", "'''", "\"", "'"]
+                    .into_iter()
+                    .find(|quote| quoted.starts_with(quote))?;
+                let interpolation: Interpolation = if flags.contains('f') {
+                    Interpolation::Braces
+                } else {
+                    Interpolation::None
+                };
+                Some(self.string_body(idx + prefix + quote.len(), quote, !flags.contains('r'), interpolation))
+            }
+            Language::JavaScript => match rest.as_bytes()[0] {
+                quote @ (b'"' | b'\'') => {
+                    let close: &str = if quote == b'"' { "\"" } else { "'" };
+                    Some(self.string_body(idx + 1, close, true, Interpolation::None))
+                }
+                b'`' => Some(self.string_body(idx + 1, "`", true, Interpolation::DollarBraces)),
+                _ => None,
+            },
+            Language::Go => match rest.as_bytes()[0] {
+                b'"' => Some(self.string_body(idx + 1, "\"", true, Interpolation::None)),
+                b'`' => Some(self.string_body(idx + 1, "`", false, Interpolation::None)),
+                b'\'' => Some(self.quoted_code(idx)),
+                _ => None,
+            },
+            Language::C => {
+                let prefix: usize = ["u8R\"", "uR\"", "UR\"", "LR\"", "R\""]
+                    .into_iter()
+                    .find(|prefix| rest.starts_with(prefix))
+                    .map_or(0, str::len);
+                if prefix > 0 && !self.follows_identifier(idx) {
+                    let delimiter_end: usize = rest[prefix..].find('(')?;
+                    let close: String = format!("){}\"", &rest[prefix..prefix + delimiter_end]);
+                    return Some(self.string_body(
+                        idx + prefix + delimiter_end + 1,
+                        &close,
+                        false,
+                        Interpolation::None,
+                    ));
+                }
+                match rest.as_bytes()[0] {
+                    b'"' => Some(self.string_body(idx + 1, "\"", true, Interpolation::None)),
+                    b'\'' => Some(self.quoted_code(idx)),
+                    _ => None,
+                }
+            }
+            Language::Shell => {
+                if rest.starts_with("$'") {
+                    return Some(self.string_body(idx + 2, "'", true, Interpolation::None));
+                }
+                match rest.as_bytes()[0] {
+                    b'\'' => Some(self.string_body(idx + 1, "'", false, Interpolation::None)),
+                    b'"' => Some(self.string_body(idx + 1, "\"", true, Interpolation::Shell)),
+                    b'\\' => Some(idx + 1 + self.char_length(idx + 1).min(self.bytes.len() - idx - 1)),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// Skips a Rust character literal, or the quote of a lifetime or label.
+    fn char_literal(&self, idx: usize) -> usize {
+        let rest: &str = &self.text[idx + 1..];
+        let mut chars = rest.char_indices();
+
+        match chars.next() {
+            Some((_, '\\')) => self.quoted_code(idx),
+            Some((_, _)) => match chars.next() {
+                Some((offset, '\'')) => idx + 1 + offset + 1,
+                _ => idx + 1,
+            },
+            None => idx + 1,
+        }
+    }
+
+    /// Skips a quoted literal that is code rather than prose, such as a C character literal.
+    fn quoted_code(&self, idx: usize) -> usize {
+        let mut end: usize = idx + 1;
+        while end < self.bytes.len() && self.bytes[end] != b'\n' {
+            match self.bytes[end] {
+                b'\\' => end += 2,
+                b'\'' => return end + 1,
+                _ => end += 1,
+            }
+        }
+        end.min(self.bytes.len())
+    }
+
+    /// Length of the escape sequence starting with the backslash at `idx`.
+    fn escape_length(&self, idx: usize) -> usize {
+        let rest: &[u8] = &self.bytes[idx + 1..];
+        let hex_digits = |count: usize| {
+            1 + rest[1..]
+                .iter()
+                .take(count)
+                .take_while(|byte| byte.is_ascii_hexdigit())
+                .count()
+        };
+
+        let length: usize = match rest.first() {
+            None => 0,
+            Some(b'x') => hex_digits(2),
+            Some(b'u') | Some(b'N') if rest.get(1) == Some(&b'{') => {
+                rest.iter().position(|byte| *byte == b'}').map_or(1, |close| close + 1)
+            }
+            Some(b'u') => hex_digits(4),
+            Some(b'U') => hex_digits(8),
+            Some(b'0'..=b'7') => rest
+                .iter()
+                .take(3)
+                .take_while(|byte| (b'0'..=b'7').contains(byte))
+                .count(),
+            Some(_) => self.char_length(idx + 1),
+        };
+        1 + length
+    }
+
+    /// Length of the interpolated expression starting at `idx`, or `None` if there is none.
+    fn interpolation_length(&self, idx: usize, interpolation: Interpolation) -> Option<usize> {
+        let rest: &str = &self.text[idx..];
+        let bracketed = |open: usize, opening: u8, closing: u8| {
+            let mut depth: usize = 0;
+            for (offset, byte) in rest.bytes().enumerate().skip(open) {
+                if byte == opening {
+                    depth += 1;
+                } else if byte == closing {
+                    depth -= 1;
+                    if depth == 0 {
+                        return offset + 1;
+                    }
+                }
+            }
+            rest.len()
+        };
+
+        match interpolation {
+            Interpolation::None => None,
+            Interpolation::Braces if rest.starts_with("{{") || rest.starts_with("}}") => Some(2),
+            Interpolation::Braces if rest.starts_with('{') => Some(bracketed(0, b'{', b'}')),
+            Interpolation::DollarBraces if rest.starts_with("${") => Some(bracketed(1, b'{', b'}')),
+            Interpolation::Shell if rest.starts_with("${") => Some(bracketed(1, b'{', b'}')),
+            Interpolation::Shell if rest.starts_with("$(") => Some(bracketed(1, b'(', b')')),
+            Interpolation::Shell if rest.starts_with('`') => {
+                Some(rest[1..].find('`').map_or(rest.len(), |close| close + 2))
+            }
+            Interpolation::Shell if rest.starts_with('$') => Some(
+                1 + rest[1..]
+                    .bytes()
+                    .take_while(|byte| is_identifier_byte(*byte) || b"@*#?$!-".contains(byte))
+                    .count()
+                    .max(1),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Records the text of a string literal whose contents start at `start`, leaving out escape
+    /// sequences and interpolated expressions, and returns the index just past `close`.
+    fn string_body(&mut self, start: usize, close: &str, escapes: bool, interpolation: Interpolation) -> usize {
+        let mut region_start: usize = start;
+        let mut idx: usize = start;
+
+        while idx < self.bytes.len() {
+            if self.text[idx..].starts_with(close) {
+                self.push_region(region_start, idx);
+                return idx + close.len();
+            }
+
+            let skip: Option<usize> = if escapes && self.bytes[idx] == b'\\' {
+                Some(self.escape_length(idx))
+            } else {
+                self.interpolation_length(idx, interpolation)
+            };
+
+            match skip {
+                Some(length) => {
+                    self.push_region(region_start, idx);
+                    idx = (idx + length).min(self.bytes.len());
+                    region_start = idx;
+                }
+                None => idx += self.char_length(idx),
+            }
+        }
+
+        self.push_region(region_start, idx);
+        idx
+    }
+
+    fn push_region(&mut self, start: usize, end: usize) {
+        if start < end {
+            self.regions.push(start..end);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{regions, Language};
+
+    fn checked(text: &str, language: Language) -> Vec<&str> {
+        regions(text, language, false)
+            .into_iter()
+            .map(|range| &text[range])
+            .collect()
+    }
+
+    #[test]
+    fn test_rust() {
+        let text: &str = r##"/// Docs with `code`.
+///
+/// ```
+/// let ignored = "in doc test";
+/// ```
+fn main<'a>(x: &'a str) {
+    let c = '"'; // trailing coment
+    let s = "line\nbreak \u{1F600}";
+    let r = r#"raw "quoted" \n"#;
+    /* block /* nested */ still */
+}
+"##;
+
+        assert_eq!(
+            checked(text, Language::Rust),
+            vec![
+                " Docs with ",
+                ".",
+                " trailing coment",
+                "line",
+                "break ",
+                "raw \"quoted\" \\n",
+                "block /* nested */ still "
+            ]
+        );
+    }
+
+    #[test]
+    fn test_python() {
+        let text: &str = "#!/usr/bin/env python\ndef f():\n    \"\"\"Docstring here.\"\"\"\n    x = f'Hello {name}!' # greet\n    return r'\\d+' + b\"bytes\\x00\"\n";
+
+        assert_eq!(
+            checked(text, Language::Python),
+            vec!["Docstring here.", "Hello ", "!", " greet", "\\d+", "bytes"]
+        );
+    }
+
+    #[test]
+    fn test_javascript_go_c_shell() {
+        let text: &str = "const s = `Hi ${user.name}, welcome`; // note\nlet t = 'it\\'s';";
+        assert_eq!(
+            checked(text, Language::JavaScript),
+            vec!["Hi ", ", welcome", " note", "it", "s"]
+        );
+
+        let text: &str = "s := `raw\\n` + \"esc\\tape\" /* block */ + string('x')";
+        assert_eq!(checked(text, Language::Go), vec!["raw\\n", "esc", "ape", "block "]);
+
+        let text: &str = "#include \"header.h\"\nchar c = '\"'; auto s = R\"x(raw)x\"; // end";
+        assert_eq!(checked(text, Language::C), vec!["raw", " end"]);
+
+        let text: &str = "echo \"Hello $USER, ${HOME} $(pwd)\" 'single $quoted' # comment\necho $# ${#arr}";
+        assert_eq!(
+            checked(text, Language::Shell),
+            vec!["Hello ", ", ", " ", "single $quoted", " comment"]
+        );
+    }
+
+    #[test]
+    fn test_identifiers() {
+        let text: &str = "const MAX_LENGHT: usize = 8;\nfn recieve_buffer(buf: &str) -> u8 { \"text\" } // parseArgs\n";
+        let checked: Vec<&str> = regions(text, Language::Rust, true)
+            .into_iter()
+            .map(|range| &text[range])
+            .collect();
+
+        assert_eq!(checked, vec!["MAX_LENGHT", "recieve_buffer", "text", " parseArgs"]);
+    }
+
+    #[test]
+    fn test_block_comment_decoration() {
+        let text: &str = "/**\n * Line one.\n * Line two.\n */";
+        assert_eq!(checked(text, Language::C), vec!["Line one.", "Line two."]);
+
+        let text: &str = "/* the café is */ int x; /* naïve /* ünclosed";
+        assert_eq!(checked(text, Language::C), vec!["the café is ", "naïve /* ünclosed"]);
+        let text: &str = "/* a /* b */ c */ d */";
+        assert_eq!(checked(text, Language::Rust), vec!["a /* b */ c "]);
+    }
+}
@@ -19,10 +19,10 @@ pub fn hash_with_seed(input: &str, seed: u32) -> u64 {
     hasher.write_u32(seed);
     input.hash(&mut hasher);
 
-    return hasher.finish();
+    hasher.finish()
 }
 
-pub fn serialize<'a, T>(
+pub fn serialize<T>(
     value: &T,
 ) -> Result<
     AlignedVec,
@@ -40,18 +40,15 @@ where
     rkyv::to_bytes::<T, 256>(value)
 }
 
-pub fn deserialize<'a, T: Archive>(
-    bytes: &'a [u8],
-) -> Result<
-    T,
-    CheckDeserializeError<
-        CheckArchiveError<
-            <<T as Archive>::Archived as rkyv::CheckBytes<DefaultValidator<'_>>>::Error,
-            DefaultValidatorError,
-        >,
-        SharedDeserializeMapError,
+type DeserializeError<'a, T> = CheckDeserializeError<
+    CheckArchiveError<
+        <<T as Archive>::Archived as rkyv::CheckBytes<DefaultValidator<'a>>>::Error,
+        DefaultValidatorError,
     >,
->
+    SharedDeserializeMapError,
+>;
+
+pub fn deserialize<'a, T: Archive>(bytes: &'a [u8]) -> Result<T, DeserializeError<'a, T>>
 where
     <T as Archive>::Archived: rkyv::CheckBytes<DefaultValidator<'a>>,
     <T as Archive>::Archived: Deserialize<T, SharedDeserializeMap>,
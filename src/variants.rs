@@ -0,0 +1,163 @@
+use std::{collections::HashMap, sync::OnceLock};
+
+use crate::config;
+
+/// The regional spellings of English that a text can be held to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Locale {
+    Us,
+    Gb,
+    Ca,
+    Au,
+}
+
+/// The names locales are given by, in the order of the columns of the variant table.
+pub const LOCALES: [&str; 4] = ["en-US", "en-GB", "en-CA", "en-AU"];
+
+const LOCALE_VALUES: [Locale; 4] = [Locale::Us, Locale::Gb, Locale::Ca, Locale::Au];
+
+impl Locale {
+    /// Reads a locale name like `en-GB` or `en_gb`.
+    pub fn parse(name: &str) -> Option<Self> {
+        LOCALES
+            .iter()
+            .position(|locale| config::same_language(locale, name))
+            .map(|column| LOCALE_VALUES[column])
+    }
+
+    fn column(self) -> usize {
+        LOCALE_VALUES.iter().position(|locale| *locale == self).expect("every locale has a column")
+    }
+}
+
+/// One word in the spelling of every locale, like `color`, `colour`, `colour` and `colour`.
+type Spellings = [String; 4];
+
+/// The variant table, read from `variants.txt` the first time it is needed.
+struct Variants {
+    spellings: Vec<Spellings>,
+    /// Indexes into `spellings` of each lowercased spelling.
+    by_word: HashMap<String, Vec<usize>>,
+}
+
+fn variants() -> &'static Variants {
+    static VARIANTS: OnceLock<Variants> = OnceLock::new();
+    VARIANTS.get_or_init(|| Variants::parse(include_str!("variants.txt")))
+}
+
+impl Variants {
+    fn parse(table: &str) -> Self {
+        let mut spellings: Vec<Spellings> = Vec::new();
+        for line in table.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let Ok(base) = <[&str; 4]>::try_from(&fields[..4.min(fields.len())]) else {
+                continue;
+            };
+            spellings.push(base.map(str::to_string));
+            for suffix in fields[4..].iter() {
+                spellings.push(base.map(|word| with_suffix(word, suffix)));
+            }
+        }
+
+        let mut by_word: HashMap<String, Vec<usize>> = HashMap::new();
+        for (idx, row) in spellings.iter().enumerate() {
+            for word in row.iter() {
+                // A word spelled the same in several locales appears in its row more than once.
+                let rows: &mut Vec<usize> = by_word.entry(word.clone()).or_default();
+                if rows.last() != Some(&idx) {
+                    rows.push(idx);
+                }
+            }
+        }
+
+        Self { spellings, by_word }
+    }
+
+    /// The locales `lower_word` is spelled for, if it is in the table at all.
+    fn locales_of(&self, lower_word: &str) -> Option<Vec<Locale>> {
+        let rows: &Vec<usize> = self.by_word.get(lower_word)?;
+
+        Some(
+            LOCALE_VALUES
+                .into_iter()
+                .filter(|locale| rows.iter().any(|row| self.spellings[*row][locale.column()] == lower_word))
+                .collect(),
+        )
+    }
+}
+
+/// Adds `suffix` to `word`, dropping a final `e` before a vowel, like `centre` to `centred`.
+fn with_suffix(word: &str, suffix: &str) -> String {
+    match (word.strip_suffix('e'), suffix.starts_with(['a', 'e', 'i', 'o', 'u'])) {
+        (Some(stem), true) => format!("{}{}", stem, suffix),
+        _ => format!("{}{}", word, suffix),
+    }
+}
+
+/// The spelling of `lower_word` for `locale` when the word is spelled for other locales only,
+/// like `color` for `colour` under en-US.
+pub fn spelling_for(lower_word: &str, locale: Locale) -> Option<&'static str> {
+    let variants: &Variants = variants();
+    let rows: &Vec<usize> = variants.by_word.get(lower_word)?;
+    if rows.iter().any(|row| variants.spellings[*row][locale.column()] == lower_word) {
+        return None;
+    }
+
+    rows.first().map(|row| variants.spellings[*row][locale.column()].as_str())
+}
+
+/// The locale most of `lower_words` are spelled for, counting only the words that are spelled
+/// differently somewhere. Ties go to the locale of the earliest of those words.
+pub fn prevailing_locale<'a, I>(lower_words: I) -> Option<Locale>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let variants: &Variants = variants();
+    // How many words are spelled for each locale, and where the first of them is.
+    let mut counts: [(usize, usize); 4] = [(0, usize::MAX); 4];
+    for (idx, lower_word) in lower_words.into_iter().enumerate() {
+        for locale in variants.locales_of(lower_word).unwrap_or_default() {
+            let (count, first) = &mut counts[locale.column()];
+            *count += 1;
+            *first = (*first).min(idx);
+        }
+    }
+
+    // Reversed, since the last of equal keys is the maximum and the table lists en-US first.
+    LOCALE_VALUES
+        .into_iter()
+        .rev()
+        .filter(|locale| counts[locale.column()].0 > 0)
+        .max_by_key(|locale| {
+            let (count, first) = counts[locale.column()];
+            (count, usize::MAX - first)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{prevailing_locale, spelling_for, with_suffix, Locale};
+
+    #[test]
+    fn test_variants() {
+        assert_eq!(Locale::parse("en_gb"), Some(Locale::Gb));
+        assert_eq!(Locale::parse("en-NZ"), None);
+        assert_eq!(with_suffix("centre", "ed"), "centred");
+        assert_eq!(with_suffix("sombre", "ly"), "sombrely");
+
+        assert_eq!(spelling_for("colour", Locale::Us), Some("color"));
+        assert_eq!(spelling_for("colour", Locale::Ca), None);
+        assert_eq!(spelling_for("colored", Locale::Gb), Some("coloured"));
+        assert_eq!(spelling_for("centring", Locale::Us), Some("centering"));
+        assert_eq!(spelling_for("organise", Locale::Ca), Some("organize"));
+        assert_eq!(spelling_for("organize", Locale::Ca), None);
+        assert_eq!(spelling_for("travelled", Locale::Au), None);
+        assert_eq!(spelling_for("hello", Locale::Us), None);
+
+        assert_eq!(prevailing_locale(["the", "colour", "of", "the", "centre"]), Some(Locale::Gb));
+        assert_eq!(prevailing_locale(["organize", "colour", "centre"]), Some(Locale::Ca));
+        assert_eq!(prevailing_locale(["color", "colour"]), Some(Locale::Us));
+        assert_eq!(prevailing_locale(["colour", "color"]), Some(Locale::Gb));
+        assert_eq!(prevailing_locale(["hello"]), None);
+    }
+}
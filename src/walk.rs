@@ -0,0 +1,149 @@
+use std::{
+    error::Error,
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use ignore::{overrides::OverrideBuilder, WalkBuilder};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// How many leading bytes are inspected when deciding whether a file is binary.
+const BINARY_SNIFF_LENGTH: usize = 8192;
+
+#[derive(Clone, Debug, Default)]
+#[readonly::make]
+pub struct WalkOptions {
+    /// Only files matching one of these globs are checked, if any are given.
+    pub includes: Vec<String>,
+    /// Files matching any of these globs are skipped.
+    pub excludes: Vec<String>,
+    /// Check hidden files and directories as well.
+    pub hidden: bool,
+    /// Check files that are ignored by `.gitignore` and `.ignore` files as well.
+    pub no_ignore: bool,
+}
+
+impl WalkOptions {
+    pub fn new(includes: Vec<String>, excludes: Vec<String>, hidden: bool, no_ignore: bool) -> Self {
+        Self {
+            includes,
+            excludes,
+            hidden,
+            no_ignore,
+        }
+    }
+}
+
+pub fn is_binary(path: &Path) -> Result<bool> {
+    let mut buffer: Vec<u8> = Vec::with_capacity(BINARY_SNIFF_LENGTH);
+    File::open(path)?
+        .take(BINARY_SNIFF_LENGTH as u64)
+        .read_to_end(&mut buffer)?;
+
+    Ok(buffer.contains(&0))
+}
+
+/// The files to check, and the errors of the entries that could not be read while walking
+/// directories for them.
+#[derive(Debug, Default)]
+pub struct Collected {
+    pub files: Vec<PathBuf>,
+    pub errors: Vec<String>,
+}
+
+/// Expands `paths` into the list of files to check. Files named explicitly are always kept unless
+/// they are binary; directories are walked recursively, honouring the globs, hidden files and
+/// ignore files according to `options`. An entry of a directory that cannot be read is recorded
+/// and skipped, so one unreadable file does not keep the others from being checked.
+pub fn collect_files(paths: &[PathBuf], options: &WalkOptions) -> Result<Collected> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    let mut errors: Vec<String> = Vec::new();
+
+    for path in paths {
+        if !path.is_dir() {
+            if !path.exists() {
+                return Err(format!("{}: No such file or directory", path.display()).into());
+            }
+            if !is_binary(path)? {
+                files.push(path.to_path_buf());
+            }
+            continue;
+        }
+
+        let mut overrides: OverrideBuilder = OverrideBuilder::new(path);
+        for include in options.includes.iter() {
+            overrides.add(include)?;
+        }
+        for exclude in options.excludes.iter() {
+            overrides.add(&format!("!{}", exclude))?;
+        }
+
+        let walker = WalkBuilder::new(path)
+            .hidden(!options.hidden)
+            .ignore(!options.no_ignore)
+            .git_ignore(!options.no_ignore)
+            .git_global(!options.no_ignore)
+            .git_exclude(!options.no_ignore)
+            .require_git(false)
+            .overrides(overrides.build()?)
+            .sort_by_file_path(|a, b| a.cmp(b))
+            .build();
+
+        for entry in walker {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    errors.push(err.to_string());
+                    continue;
+                }
+            };
+            if !entry.file_type().is_some_and(|file_type| file_type.is_file()) {
+                continue;
+            }
+            match is_binary(entry.path()) {
+                Ok(true) => {}
+                Ok(false) => files.push(entry.into_path()),
+                Err(err) => errors.push(format!("{}: {}", entry.path().display(), err)),
+            }
+        }
+    }
+
+    Ok(Collected { files, errors })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use super::{collect_files, WalkOptions};
+
+    #[test]
+    fn test_collect_files() {
+        let root: PathBuf = PathBuf::from("walk_test");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("docs/nested")).unwrap();
+        fs::create_dir_all(root.join(".hidden")).unwrap();
+        fs::write(root.join(".gitignore"), "ignored.txt\n").unwrap();
+        fs::write(root.join("docs/readme.md"), "Hello").unwrap();
+        fs::write(root.join("docs/nested/notes.txt"), "Hello").unwrap();
+        fs::write(root.join("docs/ignored.txt"), "Hello").unwrap();
+        fs::write(root.join("docs/image.bin"), [0x89, 0x50, 0x00, 0x01]).unwrap();
+        fs::write(root.join(".hidden/secret.txt"), "Hello").unwrap();
+
+        let files: Vec<PathBuf> = collect_files(std::slice::from_ref(&root), &WalkOptions::default()).unwrap().files;
+        assert_eq!(files, vec![root.join("docs/nested/notes.txt"), root.join("docs/readme.md")]);
+
+        let options: WalkOptions = WalkOptions::new(vec!["*.txt".to_string()], vec!["nested".to_string()], true, true);
+        let files: Vec<PathBuf> = collect_files(std::slice::from_ref(&root), &options).unwrap().files;
+        assert_eq!(files, vec![root.join(".hidden/secret.txt"), root.join("docs/ignored.txt")]);
+
+        let files: Vec<PathBuf> = collect_files(&[root.join("docs/ignored.txt")], &WalkOptions::default()).unwrap().files;
+        assert_eq!(files, vec![root.join("docs/ignored.txt")]);
+
+        assert!(collect_files(&[root.join("missing.txt")], &WalkOptions::default()).is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}